@@ -16,10 +16,17 @@
 // relating to use of the SAFE Network Software.
 
 use std::cmp;
+use std::collections::HashMap;
+use std::hash::Hash;
 use std::sync::{Arc, Mutex};
 
 use sodiumoxide::crypto::box_;
 
+#[cfg(feature = "debug-json")]
+use rustc_serialize::Encodable;
+#[cfg(feature = "debug-json")]
+use rustc_serialize::json;
+
 use errors::NfsError;
 use file::File;
 use xor_name::XorName;
@@ -29,6 +36,43 @@ use metadata::directory_metadata::DirectoryMetadata;
 use safe_core::client::Client;
 use safe_core::SelfEncryptionStorage;
 use self_encryption::{DataMap, SelfEncryptor};
+use utils;
+
+/// Default maximum depth up to which `DirectoryHelper::get_tree_string` and `to_tree_string_with`
+/// recurse into sub-directories
+pub const DEFAULT_TREE_DEPTH: usize = 8;
+
+/// Differences between the files of two DirectoryListings
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DirectoryDiff {
+    /// Files present only in the newer listing
+    pub added: Vec<File>,
+    /// Files present only in the older listing
+    pub removed: Vec<File>,
+    /// Files present in both the listings but which differ - (old, new)
+    pub modified: Vec<(File, File)>,
+    /// True if the listings were requested in reverse order (newer first) and were swapped
+    pub reversed: bool,
+}
+
+impl DirectoryDiff {
+    /// Returns true if there are no differences
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+/// Strategy to resolve a conflict between two different files with the same name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictStrategy {
+    /// Keep the file of the base listing, into which the other listing is merged
+    KeepBase,
+    /// Replace the file of the base listing with the file of the other listing
+    KeepOther,
+    /// Keep the file with the later modified time. The file of the base listing is kept if both
+    /// have the same modified time
+    KeepNewer,
+}
 
 /// DirectoryListing is the representation of a deserialised Directory in the network
 #[derive(Debug, RustcEncodable, RustcDecodable, PartialEq, Eq, PartialOrd, Ord, Clone)]
@@ -53,11 +97,16 @@ impl DirectoryListing {
                                                     access_level,
                                                     user_metadata,
                                                     parent_dir_key));
-        Ok(DirectoryListing {
-            metadata: meta_data,
+        Ok(DirectoryListing::with_metadata(meta_data))
+    }
+
+    /// Create a new empty DirectoryListing from the DirectoryMetadata
+    pub fn with_metadata(metadata: DirectoryMetadata) -> DirectoryListing {
+        DirectoryListing {
+            metadata: metadata,
             sub_directories: Vec::new(),
             files: Vec::new(),
-        })
+        }
     }
 
     /// Returns the DirectoryKey representing the DirectoryListing
@@ -65,11 +114,49 @@ impl DirectoryListing {
         &self.metadata.get_key()
     }
 
+    /// Returns the id of the DirectoryListing on the network. Shortcut for `get_key().get_id()`
+    pub fn id(&self) -> &XorName {
+        self.get_key().get_id()
+    }
+
+    /// Returns true if the DirectoryListing is versioned. Shortcut for
+    /// `get_key().is_versioned()`
+    pub fn is_versioned(&self) -> bool {
+        self.get_key().is_versioned()
+    }
+
+    /// Returns the AccessLevel of the DirectoryListing. Shortcut for
+    /// `get_key().get_access_level()`
+    pub fn access_level(&self) -> &::AccessLevel {
+        self.get_key().get_access_level()
+    }
+
     /// Get Directory metadata
     pub fn get_metadata(&self) -> &DirectoryMetadata {
         &self.metadata
     }
 
+    /// Get the human readable description of the directory
+    pub fn get_description(&self) -> Option<&str> {
+        self.metadata.get_description()
+    }
+
+    /// Get the maximum total size in bytes of the files in the directory, if limited
+    pub fn get_quota(&self) -> Option<u64> {
+        self.metadata.get_quota()
+    }
+
+    /// Set the maximum total size in bytes of the files in the directory. None removes the limit.
+    /// The quota is enforced by `DirectoryHelper::update`
+    pub fn set_quota(&mut self, quota: Option<u64>) {
+        self.metadata.set_quota(quota);
+    }
+
+    /// Returns the total size in bytes of the files in the directory, excluding sub-directories
+    pub fn total_size(&self) -> u64 {
+        self.files.iter().fold(0, |total, file| total + file.get_size())
+    }
+
     /// Get Directory metadata in mutable format so that it can also be updated
     pub fn get_mut_metadata(&mut self) -> &mut DirectoryMetadata {
         &mut self.metadata
@@ -110,6 +197,34 @@ impl DirectoryListing {
         Ok(try!(deserialise(&serialised_directory_listing)))
     }
 
+    /// Serialises the DirectoryListing to CBOR
+    pub fn to_cbor_bytes(&self) -> Result<Vec<u8>, NfsError> {
+        Ok(try!(serialise(self)))
+    }
+
+    /// Deserialises a DirectoryListing from the CBOR produced by `to_cbor_bytes`
+    pub fn from_cbor_bytes(data: Vec<u8>) -> Result<DirectoryListing, NfsError> {
+        Ok(try!(deserialise(&data)))
+    }
+
+    /// Returns the DirectoryListing, including its files and their metadata, as pretty-printed
+    /// JSON. This is meant for diagnostics only and is not the format stored in the network
+    #[cfg(feature = "debug-json")]
+    pub fn to_json_debug(&self) -> Result<String, NfsError> {
+        let mut json = String::new();
+        {
+            let mut encoder = json::Encoder::new_pretty(&mut json);
+            try!(self.encode(&mut encoder));
+        }
+        Ok(json)
+    }
+
+    /// Reconstructs a DirectoryListing from the JSON produced by `to_json_debug`
+    #[cfg(feature = "debug-json")]
+    pub fn from_json_debug(json: &str) -> Result<DirectoryListing, NfsError> {
+        Ok(try!(json::decode(json)))
+    }
+
     /// Encrypts the directory listing
     pub fn encrypt(&self, client: Arc<Mutex<Client>>) -> Result<Vec<u8>, NfsError> {
         let serialised_data = try!(serialise(&self));
@@ -127,13 +242,13 @@ impl DirectoryListing {
     /// Get DirectoryInfo of sub_directory within a DirectoryListing.
     /// Returns the Option<DirectoryInfo> for the directory_name from the DirectoryListing
     pub fn find_file(&self, file_name: &String) -> Option<&File> {
-        self.get_files().iter().find(|file| *file.get_name() == *file_name)
+        self.find_file_where(|file| *file.get_name() == *file_name)
     }
 
     /// Get DirectoryInfo of sub_directory within a DirectoryListing.
     /// Returns the Option<DirectoryInfo> for the directory_name from the DirectoryListing
     pub fn find_file_by_id(&self, id: &XorName) -> Option<&File> {
-        self.get_files().iter().find(|file| *file.get_id() == *id)
+        self.find_file_where(|file| *file.get_id() == *id)
     }
 
     /// Get DirectoryInfo of sub_directory within a DirectoryListing.
@@ -148,9 +263,199 @@ impl DirectoryListing {
         self.get_sub_directories().iter().find(|info| *info.get_id() == *id)
     }
 
+    /// Returns the first file for which `predicate` returns true
+    pub fn find_file_where<F: Fn(&File) -> bool>(&self, predicate: F) -> Option<&File> {
+        self.get_files().iter().find(|file| predicate(*file))
+    }
+
+    /// Returns the files for which `predicate` returns true
+    pub fn find_files_where<F: Fn(&File) -> bool>(&self, predicate: F) -> Vec<&File> {
+        self.get_files().iter().filter(|file| predicate(*file)).collect()
+    }
+
+    /// Returns the files whose size is greater than `size`
+    pub fn find_files_larger_than(&self, size: u64) -> Vec<&File> {
+        self.find_files_where(|file| file.get_size() > size)
+    }
+
+    /// Returns the files whose size is less than `size`
+    pub fn find_files_smaller_than(&self, size: u64) -> Vec<&File> {
+        self.find_files_where(|file| file.get_size() < size)
+    }
+
+    /// Returns the files whose size lies between `min` and `max`, both inclusive.
+    /// Panics if `min` is greater than `max`
+    pub fn find_files_in_size_range(&self, min: u64, max: u64) -> Vec<&File> {
+        assert!(min <= max, "Invalid size range - min is greater than max");
+        self.find_files_where(|file| {
+            let size = file.get_size();
+            min <= size && size <= max
+        })
+    }
+
+    /// Returns the files modified between `start` and `end`, both inclusive.
+    /// Panics if `start` is later than `end`
+    pub fn find_files_modified_in_range(&self, start: ::time::Tm, end: ::time::Tm) -> Vec<&File> {
+        assert!(start <= end, "Invalid time range - start is later than end");
+        self.find_files_where(|file| {
+            let modified_time = file.get_modified_time();
+            start <= *modified_time && *modified_time <= end
+        })
+    }
+
+    /// Returns the files created between `start` and `end`, both inclusive.
+    /// Panics if `start` is later than `end`
+    pub fn find_files_created_in_range(&self, start: ::time::Tm, end: ::time::Tm) -> Vec<&File> {
+        assert!(start <= end, "Invalid time range - start is later than end");
+        self.find_files_where(|file| {
+            let created_time = file.get_metadata().get_created_time();
+            start <= *created_time && *created_time <= end
+        })
+    }
+
+    /// Returns the file with the earliest modification time, None if there are no files
+    pub fn oldest_file(&self) -> Option<&File> {
+        self.get_files().iter().min_by_key(|file| file.get_modified_time().clone())
+    }
+
+    /// Returns the file with the latest modification time, None if there are no files
+    pub fn newest_file(&self) -> Option<&File> {
+        self.get_files().iter().max_by_key(|file| file.get_modified_time().clone())
+    }
+
+    /// Sorts the files in place using `compare`. Files which compare equal keep their order
+    pub fn sort_files_by<F: Fn(&File, &File) -> cmp::Ordering>(&mut self, compare: F) {
+        self.files.sort_by(|lhs, rhs| compare(lhs, rhs));
+    }
+
+    /// Sorts the files by name in ascending order
+    pub fn sort_files_by_name(&mut self) {
+        self.sort_files_by(|lhs, rhs| lhs.get_name().cmp(rhs.get_name()));
+    }
+
+    /// Sorts the files by name in descending order
+    pub fn sort_files_by_name_desc(&mut self) {
+        self.sort_files_by(|lhs, rhs| rhs.get_name().cmp(lhs.get_name()));
+    }
+
+    /// Sorts the files by modification time, oldest first
+    pub fn sort_files_by_modified_time(&mut self) {
+        self.sort_files_by(|lhs, rhs| {
+            lhs.get_modified_time().cmp(rhs.get_modified_time())
+        });
+    }
+
+    /// Sorts the files by modification time, newest first
+    pub fn sort_files_by_modified_time_desc(&mut self) {
+        self.sort_files_by(|lhs, rhs| {
+            rhs.get_modified_time().cmp(lhs.get_modified_time())
+        });
+    }
+
+    /// Sorts the files by size, smallest first
+    pub fn sort_files_by_size(&mut self) {
+        self.sort_files_by(|lhs, rhs| {
+            lhs.get_size().cmp(&rhs.get_size())
+        });
+    }
+
+    /// Sorts the files by size, largest first
+    pub fn sort_files_by_size_desc(&mut self) {
+        self.sort_files_by(|lhs, rhs| {
+            rhs.get_size().cmp(&lhs.get_size())
+        });
+    }
+
+    /// Groups the files of the DirectoryListing by the key returned by `key_fn` for each file
+    pub fn group_files_by<K, F>(&self, key_fn: F) -> HashMap<K, Vec<&File>>
+        where K: Eq + Hash,
+              F: Fn(&File) -> K
+    {
+        let mut groups = HashMap::new();
+        for file in self.get_files() {
+            groups.entry(key_fn(file)).or_insert_with(Vec::new).push(file);
+        }
+        groups
+    }
+
+    /// Groups the files by the extension of their names. Files without an extension are grouped
+    /// under an empty String
+    pub fn group_by_extension(&self) -> HashMap<String, Vec<&File>> {
+        self.group_files_by(|file| file.get_extension().unwrap_or("").to_string())
+    }
+
+    /// Groups the files by their tags. A file is listed under each of its tags, and files without
+    /// tags are not listed
+    pub fn group_by_tag(&self) -> HashMap<String, Vec<&File>> {
+        let mut groups = HashMap::new();
+        for file in self.get_files() {
+            let tags = file.get_metadata().get_tags();
+            for (index, tag) in tags.iter().enumerate() {
+                if tags[..index].contains(tag) {
+                    continue;
+                }
+                groups.entry(tag.clone()).or_insert_with(Vec::new).push(file);
+            }
+        }
+        groups
+    }
+
+    /// Groups the files by the (year, month) of their modification time. Months are 1 based
+    pub fn group_by_month_modified(&self) -> HashMap<(i32, i32), Vec<&File>> {
+        self.group_files_by(|file| {
+            let modified_time = file.get_modified_time();
+            (modified_time.tm_year + 1900, modified_time.tm_mon + 1)
+        })
+    }
+
+    /// Returns the differences between the files of `old` and `new`. Files are matched by their id
+    pub fn diff(old: &DirectoryListing, new: &DirectoryListing) -> DirectoryDiff {
+        let mut diff = DirectoryDiff::default();
+        for file in new.get_files() {
+            match old.find_file_by_id(file.get_id()) {
+                Some(old_file) => {
+                    if old_file != file {
+                        diff.modified.push((old_file.clone(), file.clone()));
+                    }
+                }
+                None => diff.added.push(file.clone()),
+            }
+        }
+        for file in old.get_files() {
+            if new.find_file_by_id(file.get_id()).is_none() {
+                diff.removed.push(file.clone());
+            }
+        }
+        diff
+    }
+
+    /// Returns the differences between the files of `old` and `new`. Files are matched by their
+    /// name, and a file is modified if its DataMap or metadata differs. A renamed file is
+    /// therefore reported as removed under its old name and added under its new name
+    pub fn diff_by_name(old: &DirectoryListing, new: &DirectoryListing) -> DirectoryDiff {
+        let mut diff = DirectoryDiff::default();
+        for file in new.get_files() {
+            match old.find_file(file.get_name()) {
+                Some(old_file) => {
+                    if old_file.get_datamap() != file.get_datamap() ||
+                       old_file.get_metadata() != file.get_metadata() {
+                        diff.modified.push((old_file.clone(), file.clone()));
+                    }
+                }
+                None => diff.added.push(file.clone()),
+            }
+        }
+        for file in old.get_files() {
+            if new.find_file(file.get_name()).is_none() {
+                diff.removed.push(file.clone());
+            }
+        }
+        diff
+    }
+
     /// If file is present in the DirectoryListing then replace it else insert it
     pub fn upsert_file(&mut self, file: File) {
-        let modified_time = file.get_metadata().get_modified_time().clone();
+        let modified_time = file.get_modified_time().clone();
         // TODO try using the below approach for efficiency - also try the same
         // in upsert_sub_directory
         //     if let Some(mut existing_file) = self.files.iter_mut().find(
@@ -208,6 +513,68 @@ impl DirectoryListing {
         Ok(())
     }
 
+    /// Returns the position of the file with the given name in the files of the listing
+    pub fn get_file_index(&self, file_name: &str) -> Option<usize> {
+        self.files.iter().position(|file| *file.get_name() == file_name)
+    }
+
+    /// Removes the file with the given name from the listing and returns it, preserving the order
+    /// of the remaining files. Returns None if there is no such file
+    pub fn pop_file(&mut self, file_name: &str) -> Option<File> {
+        let index = self.get_file_index(file_name);
+        index.map(|index| self.files.remove(index))
+    }
+
+    /// Removes all the files from the listing and returns them
+    pub fn take_all_files(&mut self) -> Vec<File> {
+        self.files.drain(..).collect()
+    }
+
+    /// Applies the files of `other` over the files of this listing without any network operation.
+    /// Files only in `other` are added. If a different file with the same name is present in both,
+    /// the conflict is resolved using `conflict`.
+    /// Returns the names of the files which had conflicts
+    pub fn merge_in(&mut self,
+                    other: &DirectoryListing,
+                    conflict: ConflictStrategy)
+                    -> Vec<String> {
+        let mut conflicts = Vec::new();
+        for incoming in other.get_files() {
+            let keep_incoming = match self.find_file(incoming.get_name()) {
+                None => true,
+                Some(existing) if existing == incoming => false,
+                Some(existing) => {
+                    conflicts.push(incoming.get_name().clone());
+                    match conflict {
+                        ConflictStrategy::KeepBase => false,
+                        ConflictStrategy::KeepOther => true,
+                        ConflictStrategy::KeepNewer => {
+                            incoming.get_modified_time() > existing.get_modified_time()
+                        }
+                    }
+                }
+            };
+            if keep_incoming {
+                let _ = self.remove_file(incoming.get_name());
+                self.upsert_file(incoming.clone());
+            }
+        }
+        conflicts
+    }
+
+    /// Returns the result of merging the files of `other` into `base` as explained for `merge_in`,
+    /// with `ConflictStrategy::KeepBase` keeping the file of `base`. The merged listing is
+    /// marked as modified now
+    pub fn merge(base: DirectoryListing,
+                 other: DirectoryListing,
+                 conflict: ConflictStrategy)
+                 -> DirectoryListing {
+        let mut merged = base;
+        let _ = merged.merge_in(&other, conflict);
+        merged.get_mut_metadata().set_modified_time(::time::now_utc());
+        merged
+    }
+
     /// Generates a nonce based on the directory_id
     pub fn generate_nonce(directory_id: &XorName) -> box_::Nonce {
         let mut nonce = [0u8; box_::NONCEBYTES];
@@ -217,17 +584,81 @@ impl DirectoryListing {
         }
         box_::Nonce(nonce)
     }
+
+    /// Returns a printable ASCII tree of the sub-directories and files of the listing, eg.
+    ///
+    /// ```text
+    /// ├── photos/
+    /// └── readme.txt (1 KiB)
+    /// ```
+    ///
+    /// A DirectoryListing does not hold the listings of its sub-directories, so they are shown
+    /// without their contents whatever `max_depth` is, and a `max_depth` of 0 returns an empty
+    /// String. Use `DirectoryHelper::get_tree_string`, or `to_tree_string_with`, to recurse into
+    /// the sub-directories
+    pub fn to_tree_string(&self, max_depth: usize) -> String {
+        self.to_tree_string_with(max_depth, &mut |_| None)
+    }
+
+    /// Returns a printable ASCII tree like `to_tree_string`, recursing into the sub-directories
+    /// returned by `load` up to a depth of `max_depth`. Sub-directories for which `load` returns
+    /// None are shown without their contents
+    pub fn to_tree_string_with<F>(&self, max_depth: usize, load: &mut F) -> String
+        where F: FnMut(&DirectoryMetadata) -> Option<DirectoryListing>
+    {
+        let mut lines = Vec::new();
+        self.append_tree_lines("", max_depth, load, &mut lines);
+        lines.join("\n")
+    }
+
+    fn append_tree_lines<F>(&self,
+                            prefix: &str,
+                            depth_left: usize,
+                            load: &mut F,
+                            lines: &mut Vec<String>)
+        where F: FnMut(&DirectoryMetadata) -> Option<DirectoryListing>
+    {
+        if depth_left == 0 {
+            return;
+        }
+        let entry_count = self.sub_directories.len() + self.files.len();
+        for (index, directory_metadata) in self.sub_directories.iter().enumerate() {
+            let is_last = index + 1 == entry_count;
+            lines.push(format!("{}{}{}/",
+                               prefix,
+                               if is_last { "└── " } else { "├── " },
+                               directory_metadata.get_name()));
+            if depth_left > 1 {
+                if let Some(sub_directory) = load(directory_metadata) {
+                    let child_prefix = format!("{}{}",
+                                               prefix,
+                                               if is_last { "    " } else { "│   " });
+                    sub_directory.append_tree_lines(&child_prefix, depth_left - 1, load, lines);
+                }
+            }
+        }
+        for (index, file) in self.files.iter().enumerate() {
+            let is_last = self.sub_directories.len() + index + 1 == entry_count;
+            lines.push(format!("{}{}{} ({})",
+                               prefix,
+                               if is_last { "└── " } else { "├── " },
+                               file.get_name(),
+                               utils::format_size(file.get_size())));
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
     use std::sync::{Arc, Mutex};
-    use super::DirectoryListing;
+    use super::{ConflictStrategy, DirectoryListing};
+    use errors::NfsError;
     use file::File;
-    use maidsafe_utilities::serialisation::{serialise, deserialise};
+    use metadata::directory_metadata::DirectoryMetadata;
     use metadata::file_metadata::FileMetadata;
     use safe_core::utility::test_utils;
     use self_encryption::DataMap;
+    use utils::test::verify_roundtrip;
 
     #[test]
     fn serialise_and_deserialise_directory_listing() {
@@ -240,9 +671,75 @@ mod test {
                                                               ::AccessLevel::Private,
                                                               None));
 
-        let serialised_data = unwrap_result!(serialise(&obj_before));
-        let obj_after = unwrap_result!(deserialise(&serialised_data));
-        assert_eq!(obj_before, obj_after);
+        verify_roundtrip(&obj_before);
+
+        let serialised = unwrap_result!(obj_before.to_cbor_bytes());
+        assert_eq!(unwrap_result!(DirectoryListing::from_cbor_bytes(serialised.clone())),
+                   obj_before);
+
+        // Corrupt or truncated data must be reported as an error rather than panic
+        let truncated = serialised[..serialised.len() / 2].to_vec();
+        match DirectoryListing::from_cbor_bytes(truncated) {
+            Err(NfsError::UnsuccessfulEncodeDecode(_)) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        let mut corrupted = serialised;
+        corrupted[0] = 0xff;
+        assert!(DirectoryListing::from_cbor_bytes(corrupted).is_err());
+    }
+
+    #[test]
+    fn key_shortcuts() {
+        for &(versioned, ref access_level) in &[(true, ::AccessLevel::Private),
+                                                (false, ::AccessLevel::Private),
+                                                (true, ::AccessLevel::Public),
+                                                (false, ::AccessLevel::Public)] {
+            let directory_listing = unwrap_result!(DirectoryListing::new("Home".to_string(),
+                                                                         10,
+                                                                         Vec::new(),
+                                                                         versioned,
+                                                                         access_level.clone(),
+                                                                         None));
+            assert_eq!(directory_listing.id(), directory_listing.get_key().get_id());
+            assert_eq!(directory_listing.is_versioned(),
+                       directory_listing.get_key().is_versioned());
+            assert_eq!(directory_listing.is_versioned(), versioned);
+            assert_eq!(directory_listing.access_level(),
+                       directory_listing.get_key().get_access_level());
+            assert_eq!(directory_listing.access_level(), access_level);
+        }
+    }
+
+    #[cfg(feature = "debug-json")]
+    #[test]
+    fn json_debug_roundtrip() {
+        let mut directory_listing =
+            unwrap_result!(DirectoryListing::new("Home".to_string(),
+                                                 10,
+                                                 Vec::new(),
+                                                 true,
+                                                 ::AccessLevel::Private,
+                                                 None));
+        let mut metadata = FileMetadata::new("file.txt".to_string(), vec![1u8, 2]);
+        metadata.set_mime_type("text/plain".to_string());
+        metadata.set_tag_value("author".to_string(), b"maidsafe".to_vec());
+        metadata.set_checksum(Some([7u8; 32]));
+        directory_listing.upsert_file(unwrap_result!(File::new(metadata,
+                                                               DataMap::Content(vec![1u8, 2, 3]))));
+        let sub_directory = unwrap_result!(DirectoryMetadata::new("Child".to_string(),
+                                                                  10,
+                                                                  true,
+                                                                  ::AccessLevel::Public,
+                                                                  Vec::new(),
+                                                                  None));
+        directory_listing.upsert_sub_directory(sub_directory);
+
+        let json = unwrap_result!(directory_listing.to_json_debug());
+        assert!(json.contains("file.txt"));
+        assert!(json.contains('\n'));
+        assert_eq!(unwrap_result!(DirectoryListing::from_json_debug(&json)),
+                   directory_listing);
+        assert!(DirectoryListing::from_json_debug("{}").is_err());
     }
 
     #[test]
@@ -301,6 +798,155 @@ mod test {
         assert_eq!(directory_listing.get_files().len(), 0);
     }
 
+    #[test]
+    fn oldest_and_newest_file() {
+        let mut directory_listing = unwrap_result!(DirectoryListing::new("Home".to_string(),
+                                                                         10,
+                                                                         Vec::new(),
+                                                                         true,
+                                                                         ::AccessLevel::Private,
+                                                                         None));
+        assert!(directory_listing.oldest_file().is_none());
+        assert!(directory_listing.newest_file().is_none());
+
+        let mut files = Vec::new();
+        for (index, name) in ["a.txt", "b.txt", "c.txt"].iter().enumerate() {
+            let mut file = unwrap_result!(File::new(FileMetadata::new(name.to_string(),
+                                                                      Vec::new()),
+                                                    DataMap::None));
+            let mut modified_time = ::time::now_utc();
+            modified_time.tm_year -= index as i32;
+            file.get_mut_metadata().set_modified_time(modified_time);
+            files.push(file);
+        }
+        directory_listing.upsert_file(files[1].clone());
+        directory_listing.upsert_file(files[0].clone());
+        directory_listing.upsert_file(files[2].clone());
+
+        assert_eq!(*unwrap_option!(directory_listing.oldest_file(), "File not found"),
+                   files[2]);
+        assert_eq!(*unwrap_option!(directory_listing.newest_file(), "File not found"),
+                   files[0]);
+    }
+
+    #[test]
+    fn find_files_in_time_range() {
+        let mut directory_listing = unwrap_result!(DirectoryListing::new("Home".to_string(),
+                                                                         10,
+                                                                         Vec::new(),
+                                                                         true,
+                                                                         ::AccessLevel::Private,
+                                                                         None));
+        let now = ::time::now_utc();
+        let mut last_year = now;
+        last_year.tm_year -= 1;
+        let mut old_file = unwrap_result!(File::new(FileMetadata::new("old.txt".to_string(),
+                                                                      Vec::new()),
+                                                    DataMap::None));
+        old_file.get_mut_metadata().set_modified_time(last_year);
+        let new_file = unwrap_result!(File::new(FileMetadata::new("new.txt".to_string(),
+                                                                  Vec::new()),
+                                                DataMap::None));
+        directory_listing.upsert_file(old_file.clone());
+        directory_listing.upsert_file(new_file.clone());
+
+        let mut start = now;
+        start.tm_mday -= 1;
+        let end = ::time::now_utc();
+        assert_eq!(directory_listing.find_files_modified_in_range(start, end),
+                   vec![&new_file]);
+        assert_eq!(directory_listing.find_files_modified_in_range(last_year, last_year),
+                   vec![&old_file]);
+        assert_eq!(directory_listing.find_files_created_in_range(start, end).len(),
+                   2);
+        assert!(directory_listing.find_files_created_in_range(last_year, last_year).is_empty());
+    }
+
+    #[test]
+    fn group_files() {
+        let mut directory_listing = unwrap_result!(DirectoryListing::new("Home".to_string(),
+                                                                         10,
+                                                                         Vec::new(),
+                                                                         true,
+                                                                         ::AccessLevel::Private,
+                                                                         None));
+        for name in &["a.txt", "b.txt", "c.jpg", "README"] {
+            let file = unwrap_result!(File::new(FileMetadata::new(name.to_string(), Vec::new()),
+                                                DataMap::None));
+            directory_listing.upsert_file(file);
+        }
+
+        let groups = directory_listing.group_by_extension();
+        assert_eq!(groups.len(), 3);
+        assert_eq!(unwrap_option!(groups.get("txt"), "Group not found").len(), 2);
+        assert_eq!(unwrap_option!(groups.get("jpg"), "Group not found").len(), 1);
+        assert_eq!(unwrap_option!(groups.get(""), "Group not found").len(), 1);
+
+        let groups = directory_listing.group_files_by(|file| file.get_name().len());
+        assert_eq!(unwrap_option!(groups.get(&5), "Group not found").len(), 3);
+
+        assert!(directory_listing.group_by_tag().is_empty());
+        for (name, tags) in vec![("a.txt", vec!["work", "draft"]),
+                                 ("b.txt", vec!["work", "work"]),
+                                 ("c.jpg", vec!["holiday"])] {
+            let mut file = unwrap_option!(directory_listing.find_file(&name.to_string()),
+                                          "File not found")
+                               .clone();
+            file.get_mut_metadata().set_tags(tags.iter().map(|tag| tag.to_string()).collect());
+            directory_listing.upsert_file(file);
+        }
+        let groups = directory_listing.group_by_tag();
+        assert_eq!(groups.len(), 3);
+        assert_eq!(unwrap_option!(groups.get("work"), "Group not found").len(), 2);
+        assert_eq!(unwrap_option!(groups.get("draft"), "Group not found").len(), 1);
+        assert_eq!(unwrap_option!(groups.get("holiday"), "Group not found")[0].get_name(),
+                   "c.jpg");
+
+        let now = ::time::now_utc();
+        let groups = directory_listing.group_by_month_modified();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(unwrap_option!(groups.get(&(now.tm_year + 1900, now.tm_mon + 1)),
+                                  "Group not found")
+                       .len(),
+                   4);
+    }
+
+    #[test]
+    fn diff_directory_listings() {
+        let mut old = unwrap_result!(DirectoryListing::new("Home".to_string(),
+                                                           10,
+                                                           Vec::new(),
+                                                           true,
+                                                           ::AccessLevel::Private,
+                                                           None));
+        let removed = unwrap_result!(File::new(FileMetadata::new("removed.txt".to_string(),
+                                                                 Vec::new()),
+                                               DataMap::None));
+        let mut modified = unwrap_result!(File::new(FileMetadata::new("modified.txt".to_string(),
+                                                                      Vec::new()),
+                                                    DataMap::None));
+        old.upsert_file(removed.clone());
+        old.upsert_file(modified.clone());
+
+        let mut new = old.clone();
+        assert!(DirectoryListing::diff(&old, &new).is_empty());
+
+        let _ = unwrap_result!(new.remove_file(removed.get_name()));
+        modified.get_mut_metadata().set_size(10);
+        new.upsert_file(modified.clone());
+        let added = unwrap_result!(File::new(FileMetadata::new("added.txt".to_string(),
+                                                               Vec::new()),
+                                             DataMap::None));
+        new.upsert_file(added.clone());
+
+        let diff = DirectoryListing::diff(&old, &new);
+        assert_eq!(diff.added, vec![added]);
+        assert_eq!(diff.removed, vec![removed]);
+        assert_eq!(diff.modified.len(), 1);
+        assert_eq!(diff.modified[0].1, modified);
+        assert!(!diff.reversed);
+    }
+
     #[test]
     fn find_upsert_remove_directory() {
         let mut directory_listing = unwrap_result!(DirectoryListing::new("Home".to_string(),
@@ -355,4 +1001,257 @@ mod test {
         assert_eq!(directory_listing.get_sub_directories().len(), 0);
     }
 
+
+    #[test]
+    fn tree_string() {
+        let new_listing = |name: &str| {
+            unwrap_result!(DirectoryListing::new(name.to_string(),
+                                                 10,
+                                                 Vec::new(),
+                                                 true,
+                                                 ::AccessLevel::Private,
+                                                 None))
+        };
+        let new_file = |name: &str, size: u64| {
+            let mut metadata = FileMetadata::new(name.to_string(), Vec::new());
+            metadata.set_size(size);
+            unwrap_result!(File::new(metadata, DataMap::None))
+        };
+        let mut photos = new_listing("photos");
+        photos.upsert_file(new_file("IMG001.jpg", 1258291));
+        photos.upsert_file(new_file("IMG002.jpg", 3565158));
+        let mut root = new_listing("root");
+        root.upsert_sub_directory(photos.get_metadata().clone());
+        root.upsert_file(new_file("readme.txt", 1024));
+
+        assert_eq!(root.to_tree_string(8), "├── photos/\n└── readme.txt (1 KiB)");
+        let mut load = |metadata: &DirectoryMetadata| {
+            if metadata.get_id() == photos.get_key().get_id() {
+                Some(photos.clone())
+            } else {
+                None
+            }
+        };
+        assert_eq!(root.to_tree_string_with(8, &mut load),
+                   "├── photos/\n│   ├── IMG001.jpg (1.2 MiB)\n│   └── IMG002.jpg (3.4 MiB)\n└── \
+                    readme.txt (1 KiB)");
+        assert_eq!(root.to_tree_string_with(1, &mut load), root.to_tree_string(1));
+        assert_eq!(root.to_tree_string(super::DEFAULT_TREE_DEPTH),
+                   "├── photos/\n└── readme.txt (1 KiB)");
+        assert_eq!(root.to_tree_string(0), "");
+    }
+
+    #[test]
+    fn merge_in_listing() {
+        let new_listing = || {
+            unwrap_result!(DirectoryListing::new("Home".to_string(),
+                                                 10,
+                                                 Vec::new(),
+                                                 true,
+                                                 ::AccessLevel::Private,
+                                                 None))
+        };
+        let new_file = |name: &str, modified_sec: i64| {
+            let mut metadata = FileMetadata::new(name.to_string(), Vec::new());
+            metadata.set_modified_time(::time::at_utc(::time::Timespec::new(modified_sec, 0)));
+            unwrap_result!(File::new(metadata, DataMap::None))
+        };
+        let mut base = new_listing();
+        let common = new_file("common.txt", 100);
+        let older = new_file("conflict_older.txt", 100);
+        let newer = new_file("conflict_newer.txt", 300);
+        base.upsert_file(common.clone());
+        base.upsert_file(older.clone());
+        base.upsert_file(newer.clone());
+
+        let mut other = new_listing();
+        let added = new_file("added.txt", 200);
+        let incoming_newer = new_file("conflict_older.txt", 200);
+        let incoming_older = new_file("conflict_newer.txt", 200);
+        other.upsert_file(common.clone());
+        other.upsert_file(added.clone());
+        other.upsert_file(incoming_newer.clone());
+        other.upsert_file(incoming_older.clone());
+
+        let mut merged = base.clone();
+        let mut conflicts = merged.merge_in(&other, ConflictStrategy::KeepNewer);
+        conflicts.sort();
+        assert_eq!(conflicts,
+                   vec!["conflict_newer.txt".to_string(), "conflict_older.txt".to_string()]);
+        assert_eq!(merged.get_files().len(), 4);
+        assert_eq!(merged.find_file(added.get_name()), Some(&added));
+        assert_eq!(merged.find_file(older.get_name()), Some(&incoming_newer));
+        assert_eq!(merged.find_file(newer.get_name()), Some(&newer));
+
+        let mut merged = base.clone();
+        let _ = merged.merge_in(&other, ConflictStrategy::KeepBase);
+        assert_eq!(merged.find_file(older.get_name()), Some(&older));
+        assert_eq!(merged.find_file(newer.get_name()), Some(&newer));
+
+        let mut merged = base.clone();
+        let _ = merged.merge_in(&other, ConflictStrategy::KeepOther);
+        assert_eq!(merged.find_file(older.get_name()), Some(&incoming_newer));
+        assert_eq!(merged.find_file(newer.get_name()), Some(&incoming_older));
+
+        let before_merge = ::time::now_utc();
+        let merged = DirectoryListing::merge(base.clone(),
+                                             other.clone(),
+                                             ConflictStrategy::KeepNewer);
+        let mut names: Vec<String> = merged.get_files()
+                                           .iter()
+                                           .map(|file| file.get_name().clone())
+                                           .collect();
+        names.sort();
+        assert_eq!(names,
+                   vec!["added.txt".to_string(),
+                        "common.txt".to_string(),
+                        "conflict_newer.txt".to_string(),
+                        "conflict_older.txt".to_string()]);
+        assert_eq!(merged.find_file(older.get_name()), Some(&incoming_newer));
+        assert_eq!(merged.find_file(newer.get_name()), Some(&newer));
+        assert_eq!(merged.get_key(), base.get_key());
+        assert!(*merged.get_metadata().get_modified_time() >= before_merge);
+    }
+
+    #[test]
+    fn pop_file() {
+        let mut listing = unwrap_result!(DirectoryListing::new("Home".to_string(),
+                                                               10,
+                                                               Vec::new(),
+                                                               true,
+                                                               ::AccessLevel::Private,
+                                                               None));
+        let files: Vec<File> = ["a.txt", "b.txt", "c.txt"]
+                                   .iter()
+                                   .map(|name| {
+                                       unwrap_result!(File::new(FileMetadata::new(name.to_string(),
+                                                                                  Vec::new()),
+                                                                DataMap::None))
+                                   })
+                                   .collect();
+        for file in &files {
+            listing.upsert_file(file.clone());
+        }
+
+        assert_eq!(listing.get_file_index("c.txt"), Some(2));
+        assert_eq!(listing.pop_file("b.txt"), Some(files[1].clone()));
+        assert_eq!(listing.get_file_index("c.txt"), Some(1));
+        assert_eq!(listing.get_file_index("b.txt"), None);
+        assert_eq!(*listing.get_files(), vec![files[0].clone(), files[2].clone()]);
+        assert!(listing.pop_file("b.txt").is_none());
+
+        assert_eq!(listing.take_all_files(), vec![files[0].clone(), files[2].clone()]);
+        assert!(listing.get_files().is_empty());
+        assert!(listing.take_all_files().is_empty());
+    }
+
+    #[test]
+    fn find_files_by_size() {
+        let mut listing = unwrap_result!(DirectoryListing::new("Home".to_string(),
+                                                               10,
+                                                               Vec::new(),
+                                                               true,
+                                                               ::AccessLevel::Private,
+                                                               None));
+        let files: Vec<File> = [10u64, 100, 1000]
+                                   .iter()
+                                   .map(|size| {
+                                       let mut metadata = FileMetadata::new(format!("{}.txt",
+                                                                                    size),
+                                                                            Vec::new());
+                                       metadata.set_size(*size);
+                                       unwrap_result!(File::new(metadata, DataMap::None))
+                                   })
+                                   .collect();
+        for file in &files {
+            listing.upsert_file(file.clone());
+        }
+
+        assert_eq!(listing.find_files_larger_than(100), vec![&files[2]]);
+        assert_eq!(listing.find_files_larger_than(1000), Vec::<&File>::new());
+        assert_eq!(listing.find_files_smaller_than(100), vec![&files[0]]);
+        assert_eq!(listing.find_files_smaller_than(10), Vec::<&File>::new());
+        assert_eq!(listing.find_files_in_size_range(10, 100), vec![&files[0], &files[1]]);
+        assert_eq!(listing.find_files_in_size_range(11, 999), vec![&files[1]]);
+    }
+
+    #[test]
+    fn find_files_with_predicate() {
+        let mut listing = unwrap_result!(DirectoryListing::new("Home".to_string(),
+                                                               10,
+                                                               Vec::new(),
+                                                               true,
+                                                               ::AccessLevel::Private,
+                                                               None));
+        let files: Vec<File> = [(10u64, "photo:beach"), (100, "photo:city"), (1000, "video:city")]
+                                   .iter()
+                                   .map(|&(size, user_metadata)| {
+                                       let mut metadata =
+                                           FileMetadata::new(format!("{}.bin", size),
+                                                             user_metadata.as_bytes().to_vec());
+                                       metadata.set_size(size);
+                                       unwrap_result!(File::new(metadata, DataMap::None))
+                                   })
+                                   .collect();
+        for file in &files {
+            listing.upsert_file(file.clone());
+        }
+
+        let size_in_range = |file: &File| {
+            let size = file.get_size();
+            50 <= size && size <= 5000
+        };
+        assert_eq!(listing.find_files_where(&size_in_range), vec![&files[1], &files[2]]);
+        assert_eq!(listing.find_file_where(&size_in_range), Some(&files[1]));
+
+        let is_photo = |file: &File| file.get_metadata().get_user_metadata().starts_with(b"photo:");
+        assert_eq!(listing.find_files_where(is_photo), vec![&files[0], &files[1]]);
+
+        assert_eq!(listing.find_files_where(|_| false), Vec::<&File>::new());
+        assert_eq!(listing.find_file_where(|_| false), None);
+    }
+
+    #[test]
+    fn sort_files() {
+        let mut listing = unwrap_result!(DirectoryListing::new("Home".to_string(),
+                                                               10,
+                                                               Vec::new(),
+                                                               true,
+                                                               ::AccessLevel::Private,
+                                                               None));
+        // (name, size, modified time in seconds since the epoch)
+        let files = [("c.txt", 30u64, 500i64),
+                     ("a.txt", 50, 100),
+                     ("e.txt", 10, 400),
+                     ("b.txt", 40, 200),
+                     ("d.txt", 20, 300)];
+        for &(name, size, modified_time) in files.iter() {
+            let mut metadata = FileMetadata::new(name.to_string(), Vec::new());
+            metadata.set_size(size);
+            metadata.set_modified_time(::time::at_utc(::time::Timespec::new(modified_time, 0)));
+            listing.get_mut_files().push(unwrap_result!(File::new(metadata, DataMap::None)));
+        }
+        let names = |listing: &DirectoryListing| {
+            listing.get_files()
+                   .iter()
+                   .map(|file| file.get_name().clone())
+                   .collect::<Vec<String>>()
+                   .join(",")
+        };
+
+        listing.sort_files_by_name();
+        assert_eq!(names(&listing), "a.txt,b.txt,c.txt,d.txt,e.txt");
+        listing.sort_files_by_name_desc();
+        assert_eq!(names(&listing), "e.txt,d.txt,c.txt,b.txt,a.txt");
+        listing.sort_files_by_modified_time();
+        assert_eq!(names(&listing), "a.txt,b.txt,d.txt,e.txt,c.txt");
+        listing.sort_files_by_modified_time_desc();
+        assert_eq!(names(&listing), "c.txt,e.txt,d.txt,b.txt,a.txt");
+        listing.sort_files_by_size();
+        assert_eq!(names(&listing), "e.txt,d.txt,c.txt,b.txt,a.txt");
+        listing.sort_files_by_size_desc();
+        assert_eq!(names(&listing), "a.txt,b.txt,c.txt,d.txt,e.txt");
+        listing.sort_files_by(|lhs, rhs| lhs.get_extension().cmp(&rhs.get_extension()));
+        assert_eq!(names(&listing), "a.txt,b.txt,c.txt,d.txt,e.txt");
+    }
 }
@@ -15,20 +15,30 @@
 // Please review the Licences for the specific language governing permissions and limitations
 // relating to use of the SAFE Network Software.
 
+use std::error::Error;
 use std::fmt;
+use std::io;
 
 use maidsafe_utilities::serialisation::SerialisationError;
+use rustc_serialize::base64::FromBase64Error;
+use rustc_serialize::json;
 use safe_core::errors::{CoreError, CLIENT_ERROR_START_RANGE};
 
 /// Intended for converting NFS Errors into numeric codes for propagating some error information
 /// across FFI boundaries and specially to C.
 pub const NFS_ERROR_START_RANGE: i32 = CLIENT_ERROR_START_RANGE - 500;
 
-/// NFS Errors
+/// NFS Errors. Errors from other crates are stringified on conversion, so that NfsError is
+/// `Send + Sync`
 #[allow(variant_size_differences)] // TODO
 pub enum NfsError {
     /// Client Error
-    CoreError(CoreError),
+    CoreError {
+        /// Error code of the CoreError
+        code: i32,
+        /// Description of the CoreError
+        description: String,
+    },
     /// If Directory already exists with the same name in the same level
     DirectoryAlreadyExistsWithSameName,
     /// Destination is Same as the Source
@@ -48,18 +58,110 @@ pub enum NfsError {
     /// Unexpected error
     Unexpected(String),
     /// Unsuccessful Serialisation or Deserialisation
-    UnsuccessfulEncodeDecode(SerialisationError),
+    UnsuccessfulEncodeDecode(String),
+    /// Data could not be encoded to or decoded from the requested format (eg., JSON)
+    InvalidEncoding(String),
+    /// Requested version does not exist
+    VersionNotFound,
+    /// Content written does not match the expected hash
+    IntegrityCheckFailed,
+    /// A batch operation failed for some of its items. Holds the number of items which succeeded
+    /// and the name of each item which failed along with the reason
+    PartialFailure {
+        /// Number of items processed successfully
+        succeeded: usize,
+        /// Name and error of each item which failed
+        errors: Vec<(String, NfsError)>,
+    },
+    /// Total size of the files in a directory would exceed its quota
+    QuotaExceeded {
+        /// Total size of the files in bytes
+        used: u64,
+        /// Quota of the directory in bytes
+        limit: u64,
+    },
+    /// A write lock is held on the file
+    FileLocked,
+    /// The file was modified on the network since it was fetched
+    ConcurrentModification,
+    /// Encrypted content could not be decrypted, eg. because the key is wrong or the content was
+    /// tampered with
+    DecryptionFailed,
+    /// An error along with the path of the file or directory which triggered it
+    WithContext {
+        /// Path of the file or directory
+        context: String,
+        /// The original error
+        cause: Box<NfsError>,
+    },
+}
+
+impl NfsError {
+    /// Attaches the path of the file or directory which triggered the error. If the error already
+    /// has a context, `path` is prepended to it as a parent directory
+    pub fn with_context<S: Into<String>>(self, path: S) -> NfsError {
+        match self {
+            NfsError::WithContext { context, cause } => {
+                NfsError::WithContext {
+                    context: format!("{}/{}", path.into(), context),
+                    cause: cause,
+                }
+            }
+            error => {
+                NfsError::WithContext {
+                    context: path.into(),
+                    cause: Box::new(error),
+                }
+            }
+        }
+    }
+
+    /// Returns the original error, without any context attached by `with_context`
+    pub fn root_error(&self) -> &NfsError {
+        match *self {
+            NfsError::WithContext { ref cause, .. } => cause.root_error(),
+            ref error => error,
+        }
+    }
 }
 
 impl From<CoreError> for NfsError {
     fn from(error: CoreError) -> NfsError {
-        NfsError::CoreError(error)
+        let description = format!("{:?}", error);
+        NfsError::CoreError {
+            code: error.into(),
+            description: description,
+        }
     }
 }
 
 impl From<SerialisationError> for NfsError {
     fn from(error: SerialisationError) -> NfsError {
-        NfsError::UnsuccessfulEncodeDecode(error)
+        NfsError::UnsuccessfulEncodeDecode(format!("{:?}", error))
+    }
+}
+
+impl From<json::EncoderError> for NfsError {
+    fn from(error: json::EncoderError) -> NfsError {
+        NfsError::InvalidEncoding(format!("{:?}", error))
+    }
+}
+
+impl From<json::DecoderError> for NfsError {
+    fn from(error: json::DecoderError) -> NfsError {
+        NfsError::InvalidEncoding(format!("{:?}", error))
+    }
+}
+
+impl From<FromBase64Error> for NfsError {
+    fn from(error: FromBase64Error) -> NfsError {
+        NfsError::InvalidEncoding(format!("{:?}", error))
+    }
+}
+
+impl From<io::Error> for NfsError {
+    fn from(error: io::Error) -> NfsError {
+        NfsError::Unexpected(format!("{:?}", error))
     }
 }
 
@@ -72,7 +174,7 @@ impl<'a> From<&'a str> for NfsError {
 impl Into<i32> for NfsError {
     fn into(self) -> i32 {
         match self {
-            NfsError::CoreError(error) => error.into(),
+            NfsError::CoreError { code, .. } => code,
             NfsError::DirectoryAlreadyExistsWithSameName => NFS_ERROR_START_RANGE - 1,
             NfsError::DestinationAndSourceAreSame => NFS_ERROR_START_RANGE - 2,
             NfsError::DirectoryNotFound => NFS_ERROR_START_RANGE - 3,
@@ -83,6 +185,15 @@ impl Into<i32> for NfsError {
             NfsError::ParameterIsNotValid => NFS_ERROR_START_RANGE - 8,
             NfsError::Unexpected(_) => NFS_ERROR_START_RANGE - 9,
             NfsError::UnsuccessfulEncodeDecode(_) => NFS_ERROR_START_RANGE - 10,
+            NfsError::InvalidEncoding(_) => NFS_ERROR_START_RANGE - 11,
+            NfsError::VersionNotFound => NFS_ERROR_START_RANGE - 12,
+            NfsError::PartialFailure { .. } => NFS_ERROR_START_RANGE - 13,
+            NfsError::IntegrityCheckFailed => NFS_ERROR_START_RANGE - 14,
+            NfsError::QuotaExceeded { .. } => NFS_ERROR_START_RANGE - 15,
+            NfsError::FileLocked => NFS_ERROR_START_RANGE - 16,
+            NfsError::ConcurrentModification => NFS_ERROR_START_RANGE - 17,
+            NfsError::DecryptionFailed => NFS_ERROR_START_RANGE - 18,
+            NfsError::WithContext { cause, .. } => (*cause).into(),
         }
     }
 }
@@ -90,7 +201,9 @@ impl Into<i32> for NfsError {
 impl fmt::Debug for NfsError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            NfsError::CoreError(ref error) => write!(f, "NfsError::CoreError -> {:?}", error),
+            NfsError::CoreError { ref description, .. } => {
+                write!(f, "NfsError::CoreError -> {}", description)
+            }
             NfsError::DirectoryAlreadyExistsWithSameName => {
                 write!(f, "NfsError::DirectoryAlreadyExistsWithSameName")
             }
@@ -107,8 +220,137 @@ impl fmt::Debug for NfsError {
             NfsError::ParameterIsNotValid => write!(f, "NfsError::ParameterIsNotValid"),
             NfsError::Unexpected(ref error) => write!(f, "NfsError::Unexpected -> {:?}", error),
             NfsError::UnsuccessfulEncodeDecode(ref error) => {
-                write!(f, "NfsError::UnsuccessfulEncodeDecode -> {:?}", error)
+                write!(f, "NfsError::UnsuccessfulEncodeDecode -> {}", error)
+            }
+            NfsError::InvalidEncoding(ref error) => {
+                write!(f, "NfsError::InvalidEncoding -> {:?}", error)
+            }
+            NfsError::VersionNotFound => write!(f, "NfsError::VersionNotFound"),
+            NfsError::IntegrityCheckFailed => write!(f, "NfsError::IntegrityCheckFailed"),
+            NfsError::PartialFailure { succeeded, ref errors } => {
+                write!(f,
+                       "NfsError::PartialFailure -> succeeded: {}, errors: {:?}",
+                       succeeded,
+                       errors)
+            }
+            NfsError::QuotaExceeded { used, limit } => {
+                write!(f, "NfsError::QuotaExceeded -> used: {}, limit: {}", used, limit)
+            }
+            NfsError::FileLocked => write!(f, "NfsError::FileLocked"),
+            NfsError::ConcurrentModification => write!(f, "NfsError::ConcurrentModification"),
+            NfsError::DecryptionFailed => write!(f, "NfsError::DecryptionFailed"),
+            NfsError::WithContext { ref context, ref cause } => {
+                write!(f, "NfsError::WithContext -> {}: {:?}", context, cause)
+            }
+        }
+    }
+}
+
+impl fmt::Display for NfsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            NfsError::CoreError { ref description, .. } => {
+                write!(f, "Core error: {}", description)
+            }
+            NfsError::Unexpected(ref error) => write!(f, "Unexpected error: {}", error),
+            NfsError::UnsuccessfulEncodeDecode(ref error) => {
+                write!(f, "Serialisation error: {}", error)
+            }
+            NfsError::InvalidEncoding(ref error) => write!(f, "Invalid encoding: {}", error),
+            NfsError::PartialFailure { succeeded, ref errors } => {
+                try!(write!(f,
+                            "{} items succeeded and {} failed: ",
+                            succeeded,
+                            errors.len()));
+                for (index, &(ref name, ref error)) in errors.iter().enumerate() {
+                    if index > 0 {
+                        try!(write!(f, ", "));
+                    }
+                    try!(write!(f, "{} ({})", name, error));
+                }
+                Ok(())
+            }
+            NfsError::QuotaExceeded { used, limit } => {
+                write!(f, "Directory quota exceeded: {} of {} bytes used", used, limit)
+            }
+            NfsError::WithContext { ref context, ref cause } => write!(f, "{}: {}", context, cause),
+            _ => write!(f, "{}", self.description()),
+        }
+    }
+}
+
+impl Error for NfsError {
+    fn description(&self) -> &str {
+        match *self {
+            NfsError::CoreError { .. } => "Core error",
+            NfsError::DirectoryAlreadyExistsWithSameName => {
+                "Directory already exists with the same name"
             }
+            NfsError::DestinationAndSourceAreSame => "Destination is the same as the source",
+            NfsError::DirectoryNotFound => "Directory not found",
+            NfsError::FileAlreadyExistsWithSameName => "File already exists with the same name",
+            NfsError::FileDoesNotMatch => "File does not match the file in the directory",
+            NfsError::FileNotFound => "File not found",
+            NfsError::InvalidRangeSpecified => "Invalid byte range specified",
+            NfsError::ParameterIsNotValid => "Parameter is not valid",
+            NfsError::Unexpected(_) => "Unexpected error",
+            NfsError::UnsuccessfulEncodeDecode(_) => "Serialisation error",
+            NfsError::InvalidEncoding(_) => "Invalid encoding",
+            NfsError::VersionNotFound => "Version not found",
+            NfsError::IntegrityCheckFailed => "Content does not match the expected hash",
+            NfsError::PartialFailure { .. } => "Operation failed for some of the items",
+            NfsError::QuotaExceeded { .. } => "Directory quota exceeded",
+            NfsError::FileLocked => "File is locked for writing",
+            NfsError::ConcurrentModification => "File was modified by another session",
+            NfsError::DecryptionFailed => "Content could not be decrypted",
+            NfsError::WithContext { ref cause, .. } => cause.description(),
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        match *self {
+            NfsError::WithContext { ref cause, .. } => Some(&**cause as &Error),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::error::Error;
+    use std::thread;
+    use safe_core::errors::CoreError;
+    use super::NfsError;
+
+    #[test]
+    fn send_across_threads() {
+        let error = unwrap_result!(thread::spawn(|| NfsError::Unexpected("failed".to_string()))
+                                       .join());
+        assert_eq!(format!("{}", error), "Unexpected error: failed");
+
+        let boxed: Box<Error + Send + Sync> = From::from(NfsError::FileNotFound);
+        let boxed = unwrap_result!(thread::spawn(move || boxed.description().to_string()).join());
+        assert_eq!(boxed, "File not found");
+
+        let expected_code: i32 = CoreError::SymmetricDecipherFailure.into();
+        let error = NfsError::from(CoreError::SymmetricDecipherFailure);
+        let error = unwrap_result!(thread::spawn(move || error).join());
+        let code: i32 = error.into();
+        assert_eq!(code, expected_code);
+    }
+
+    #[test]
+    fn error_with_context() {
+        let error = NfsError::FileNotFound.with_context("file.txt").with_context("dir");
+        assert_eq!(format!("{}", error), "dir/file.txt: File not found");
+        assert_eq!(error.description(), "File not found");
+        assert!(error.cause().is_some());
+        match *error.root_error() {
+            NfsError::FileNotFound => (),
+            ref error => panic!("Unexpected error {:?}", error),
         }
+        let code: i32 = error.into();
+        let expected: i32 = NfsError::FileNotFound.into();
+        assert_eq!(code, expected);
     }
 }
@@ -18,18 +18,24 @@
 use std::fmt;
 
 use errors::NfsError;
+use maidsafe_utilities::serialisation::{serialise, deserialise};
 use metadata::file_metadata::FileMetadata;
+use rustc_serialize::{Decodable, Decoder};
 use safe_core::utility;
 use self_encryption::DataMap;
+use time::Tm;
+use utils;
 use xor_name::XorName;
 
 /// Representation of a File to be put into the network. Could be text, music, video etc any kind
 /// of file
-#[derive(RustcEncodable, RustcDecodable, PartialEq, Eq, PartialOrd, Ord, Clone)]
+#[derive(RustcEncodable, PartialEq, Eq, PartialOrd, Ord, Clone)]
 pub struct File {
     id: XorName,
     metadata: FileMetadata,
     datamap: DataMap,
+    encrypted_metadata: Vec<u8>,
+    version: u64,
 }
 
 impl File {
@@ -39,6 +45,8 @@ impl File {
             id: XorName::new(try!(utility::generate_random_array_u8_64())),
             metadata: metadata,
             datamap: datamap,
+            encrypted_metadata: Vec::new(),
+            version: 0,
         })
     }
 
@@ -52,6 +60,27 @@ impl File {
         self.metadata.get_name()
     }
 
+    /// Get the extension of the File name, i.e. the part after the last '.'
+    /// Returns None if the name has no extension
+    pub fn get_extension(&self) -> Option<&str> {
+        let name = self.get_name();
+        match name.rfind('.') {
+            Some(index) if index + 1 < name.len() => Some(&name[index + 1..]),
+            _ => None,
+        }
+    }
+
+    /// Get the size of the File. Shortcut for `get_metadata().get_size()`
+    pub fn get_size(&self) -> u64 {
+        self.metadata.get_size()
+    }
+
+    /// Get the time of modification of the File. Shortcut for
+    /// `get_metadata().get_modified_time()`
+    pub fn get_modified_time(&self) -> &Tm {
+        self.metadata.get_modified_time()
+    }
+
     /// Get metadata associated with the file
     pub fn get_metadata(&self) -> &FileMetadata {
         &self.metadata
@@ -72,6 +101,62 @@ impl File {
     pub fn set_datamap(&mut self, datamap: DataMap) {
         self.datamap = datamap;
     }
+
+    /// Returns true if the metadata of the File is held encrypted
+    pub fn is_metadata_encrypted(&self) -> bool {
+        !self.encrypted_metadata.is_empty()
+    }
+
+    /// Get the encrypted metadata of the File. Empty if the metadata is not encrypted
+    pub fn get_encrypted_metadata(&self) -> &Vec<u8> {
+        &self.encrypted_metadata
+    }
+
+    /// Set the encrypted metadata of the File
+    pub fn set_encrypted_metadata(&mut self, encrypted_metadata: Vec<u8>) {
+        self.encrypted_metadata = encrypted_metadata;
+    }
+
+    /// Get the number of times the content of the File has been written. This is used to detect
+    /// concurrent modification of the File by another session
+    pub fn get_version(&self) -> u64 {
+        self.version
+    }
+
+    /// Increments the version of the File. Invoked by `Writer::close` each time the content is
+    /// written
+    pub fn increment_version(&mut self) {
+        self.version += 1;
+    }
+
+    /// Serialises the File to CBOR
+    pub fn to_cbor_bytes(&self) -> Result<Vec<u8>, NfsError> {
+        Ok(try!(serialise(self)))
+    }
+
+    /// Deserialises a File from the CBOR produced by `to_cbor_bytes`
+    pub fn from_cbor_bytes(data: Vec<u8>) -> Result<File, NfsError> {
+        Ok(try!(deserialise(&data)))
+    }
+}
+
+impl Decodable for File {
+    // encrypted_metadata and version were added after the initial format and are defaulted when
+    // absent, so that files stored by earlier versions of the crate can still be read
+    fn decode<D: Decoder>(d: &mut D) -> Result<File, D::Error> {
+        d.read_struct("File", 5, |d| {
+            Ok(File {
+                id: try!(d.read_struct_field("id", 0, |d| Decodable::decode(d))),
+                metadata: try!(d.read_struct_field("metadata", 1, |d| Decodable::decode(d))),
+                datamap: try!(d.read_struct_field("datamap", 2, |d| Decodable::decode(d))),
+                encrypted_metadata: try!(utils::read_struct_field_or(d,
+                                                                     "encrypted_metadata",
+                                                                     3,
+                                                                     Vec::new)),
+                version: try!(utils::read_struct_field_or(d, "version", 4, || 0)),
+            })
+        })
+    }
 }
 
 impl fmt::Debug for File {
@@ -85,7 +170,43 @@ mod test {
     use super::*;
     use maidsafe_utilities::serialisation::{serialise, deserialise};
     use metadata::file_metadata::FileMetadata;
+    use rustc_serialize::{Encodable, Encoder};
     use self_encryption::DataMap;
+    use utils::test::verify_roundtrip;
+    use xor_name::XorName;
+
+    // File as encoded by the initial version of the crate
+    struct BaselineFile {
+        id: XorName,
+        metadata: FileMetadata,
+        datamap: DataMap,
+    }
+
+    impl Encodable for BaselineFile {
+        fn encode<E: Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
+            e.emit_struct("File", 3, |e| {
+                try!(e.emit_struct_field("id", 0, |e| self.id.encode(e)));
+                try!(e.emit_struct_field("metadata", 1, |e| self.metadata.encode(e)));
+                try!(e.emit_struct_field("datamap", 2, |e| self.datamap.encode(e)));
+                Ok(())
+            })
+        }
+    }
+
+    #[test]
+    fn deserialise_baseline_file() {
+        let baseline = BaselineFile {
+            id: XorName::new([7u8; 64]),
+            metadata: FileMetadata::new("Home".to_string(), Vec::new()),
+            datamap: DataMap::Content(vec![1u8, 2, 3]),
+        };
+        let file: File = unwrap_result!(deserialise(&unwrap_result!(serialise(&baseline))));
+        assert_eq!(*file.get_id(), baseline.id);
+        assert_eq!(*file.get_metadata(), baseline.metadata);
+        assert_eq!(*file.get_datamap(), baseline.datamap);
+        assert!(!file.is_metadata_encrypted());
+        assert_eq!(file.get_version(), 0);
+    }
 
     #[test]
     fn serialise_deserialise() {
@@ -95,8 +216,32 @@ mod test {
                                                                         .to_string()
                                                                         .into_bytes()),
                                                   DataMap::None));
-        let serialised_data = unwrap_result!(serialise(&obj_before));
-        let obj_after = unwrap_result!(deserialise(&serialised_data));
-        assert_eq!(obj_before, obj_after);
+        verify_roundtrip(&obj_before);
+
+        let serialised = unwrap_result!(obj_before.to_cbor_bytes());
+        assert_eq!(unwrap_result!(File::from_cbor_bytes(serialised)), obj_before);
+        assert!(File::from_cbor_bytes(vec![1u8, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn file_extension() {
+        let file_with_name = |name: &str| {
+            unwrap_result!(File::new(FileMetadata::new(name.to_string(), Vec::new()),
+                                     DataMap::None))
+        };
+        assert_eq!(file_with_name("photo.tar.gz").get_extension(), Some("gz"));
+        assert_eq!(file_with_name("readme").get_extension(), None);
+        assert_eq!(file_with_name("trailing.").get_extension(), None);
+    }
+
+    #[test]
+    fn metadata_shortcuts() {
+        let mut metadata = FileMetadata::new("file.txt".to_string(), Vec::new());
+        metadata.set_size(42);
+        let file = unwrap_result!(File::new(metadata, DataMap::None));
+        assert_eq!(file.get_size(), 42);
+        assert_eq!(file.get_size(), file.get_metadata().get_size());
+        assert_eq!(file.get_modified_time(), file.get_metadata().get_modified_time());
+        assert_eq!(file.get_name(), file.get_metadata().get_name());
     }
 }
@@ -15,27 +15,101 @@
 // Please review the Licences for the specific language governing permissions and limitations
 // relating to use of the SAFE Network Software.
 
+use std::cmp;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
 
 use errors::NfsError;
-use directory_listing::DirectoryListing;
+use file::File;
+use helper::file_helper::FileHelper;
+use helper::reader::Reader;
+use directory_listing::{DirectoryDiff, DirectoryListing};
 use xor_name::XorName;
 use maidsafe_utilities::serialisation::{serialise, deserialise};
 use metadata::directory_key::DirectoryKey;
+use metadata::directory_metadata::{DirectoryMetadata, MAX_DESCRIPTION_SIZE};
 use routing::{ImmutableData, ImmutableDataType, StructuredData, Data, DataRequest};
 use safe_core::client::Client;
 use safe_core::errors::CoreError;
 use safe_core::structured_data_operations::{unversioned, versioned};
+use sodiumoxide::crypto::hash::sha256;
+use sodiumoxide::crypto::sign;
+use utils;
+
+/// Maximum number of threads used by `DirectoryHelper::get_batch`
+pub const BATCH_FETCH_THREADS: usize = 8;
+
+/// Returned by the visitors passed to `DirectoryHelper::walk` and
+/// `DirectoryHelper::walk_with_directories` to control the traversal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalkControl {
+    /// Continue with the next file, or descend into the sub-directory
+    Continue,
+    /// Skip the remaining files and the sub-directories of the current directory. Returned for a
+    /// sub-directory, skips that sub-directory without fetching it
+    SkipDirectory,
+    /// End the traversal
+    Stop,
+}
+
+/// Summary of a `DirectoryHelper::export_tree` operation
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ExportStats {
+    /// Number of files written to the local filesystem
+    pub total_files: u64,
+    /// Number of bytes written to the local filesystem
+    pub total_bytes: u64,
+    /// Paths, relative to the exported root, of the entries whose names can not be used as local
+    /// file or directory names
+    pub skipped: Vec<String>,
+}
+
+/// Summary of a `DirectoryHelper::import_tree` operation
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ImportStats {
+    /// Number of files uploaded to the network
+    pub files_uploaded: u64,
+    /// Number of bytes uploaded to the network
+    pub bytes_transferred: u64,
+    /// Local paths of the entries which could not be imported, either because they could not be
+    /// read or because their names are not valid UTF-8
+    pub failed: Vec<String>,
+}
 
 /// DirectoryHelper provides helper functions to perform Operations on Directory
 pub struct DirectoryHelper {
     client: Arc<Mutex<Client>>,
+    cache: Option<Mutex<DirectoryCache>>,
 }
 
 impl DirectoryHelper {
     /// Create a new DirectoryHelper instance
     pub fn new(client: Arc<Mutex<Client>>) -> DirectoryHelper {
-        DirectoryHelper { client: client }
+        DirectoryHelper {
+            client: client,
+            cache: None,
+        }
+    }
+
+    /// Create a new DirectoryHelper instance which holds up to `cache_size` of the most recently
+    /// fetched DirectoryListings in memory, so that `get` and `get_by_version` do not fetch them
+    /// from the network again. A cached latest version is discarded when the directory is updated
+    /// through this DirectoryHelper, but changes made through other DirectoryHelpers or clients
+    /// are not seen until it is evicted. A `cache_size` of 0 disables the cache
+    pub fn new_with_cache(client: Arc<Mutex<Client>>, cache_size: usize) -> DirectoryHelper {
+        DirectoryHelper {
+            client: client,
+            cache: if cache_size == 0 {
+                None
+            } else {
+                Some(Mutex::new(DirectoryCache::new(cache_size)))
+            },
+        }
     }
 
     /// Creates a Directory in the network.
@@ -52,24 +126,34 @@ impl DirectoryHelper {
                   access_level: ::AccessLevel,
                   parent_directory: Option<&mut DirectoryListing>)
                   -> Result<(DirectoryListing, Option<DirectoryListing>), NfsError> {
+        let metadata = try!(DirectoryMetadata::new(directory_name,
+                                                   tag_type,
+                                                   versioned,
+                                                   access_level,
+                                                   user_metadata,
+                                                   None));
+        self.create_with_metadata(metadata, parent_directory)
+    }
 
+    /// Creates a Directory in the network using the DirectoryMetadata passed.
+    /// If parent_directory is passed, the parent_dir_key of the metadata is set to the key of the
+    /// parent_directory and the parent_directory is updated as explained for `create`
+    /// Returns (created_directory, Option<parent_directory's parent>)
+    pub fn create_with_metadata(&self,
+                                mut metadata: DirectoryMetadata,
+                                parent_directory: Option<&mut DirectoryListing>)
+                                -> Result<(DirectoryListing, Option<DirectoryListing>), NfsError> {
         if parent_directory.iter()
                            .next()
-                           .and_then(|dir| dir.find_sub_directory(&directory_name))
+                           .and_then(|dir| dir.find_sub_directory(metadata.get_name()))
                            .is_some() {
             return Err(NfsError::DirectoryAlreadyExistsWithSameName);
         }
+        if let Some(directory) = parent_directory.iter().next() {
+            metadata.set_parent_dir_key(Some(directory.get_key().clone()));
+        }
 
-        let directory = try!(DirectoryListing::new(directory_name,
-                                                   tag_type,
-                                                   user_metadata,
-                                                   versioned,
-                                                   access_level,
-                                                   parent_directory.iter()
-                                                                   .next()
-                                                                   .map(|directory| {
-                                                                       directory.get_key().clone()
-                                                                   })));
+        let directory = DirectoryListing::with_metadata(metadata);
 
         let structured_data = try!(self.save_directory_listing(&directory));
         debug!("Posting PUT request to network to save structured data for directory ...");
@@ -82,6 +166,85 @@ impl DirectoryHelper {
         }
     }
 
+    /// Creates an independent copy of the directory tree rooted at `source_root` as a sub
+    /// directory named `new_name` of `dest_parent`. Every directory and file of the copy gets a
+    /// fresh id, so that changes to either tree do not affect the other. The content of the files
+    /// is shared, as it is held in immutable data.
+    /// Returns the root of the copy
+    pub fn deep_clone(&self,
+                      source_root: &DirectoryListing,
+                      dest_parent: &mut DirectoryListing,
+                      new_name: String)
+                      -> Result<DirectoryListing, NfsError> {
+        self.copy_tree(source_root, Some(dest_parent), new_name)
+    }
+
+    /// Creates an independent copy of the directory tree rooted at `source`, named `new_name`, as
+    /// done by `deep_clone`. The copy has the same versioning and AccessLevel as `source`. If
+    /// `dest_parent` is passed, the copy is added to it and the updated parent is saved to the
+    /// network; it can be fetched again using `get`.
+    /// Returns the root of the copy
+    pub fn copy_directory(&self,
+                          source: &DirectoryListing,
+                          new_name: String,
+                          dest_parent: Option<&DirectoryListing>)
+                          -> Result<DirectoryListing, NfsError> {
+        match dest_parent {
+            Some(dest_parent) => {
+                let mut dest_parent = dest_parent.clone();
+                self.copy_tree(source, Some(&mut dest_parent), new_name)
+            }
+            None => self.copy_tree(source, None, new_name),
+        }
+    }
+
+    /// Copies the directory tree rooted at `source_root` as explained for `deep_clone`
+    fn copy_tree(&self,
+                 source_root: &DirectoryListing,
+                 dest_parent: Option<&mut DirectoryListing>,
+                 new_name: String)
+                 -> Result<DirectoryListing, NfsError> {
+        let source_key = source_root.get_key();
+        let mut metadata = try!(DirectoryMetadata::new(new_name,
+                                                       source_key.get_type_tag(),
+                                                       source_key.is_versioned(),
+                                                       source_key.get_access_level().clone(),
+                                                       source_root.get_metadata()
+                                                                  .get_user_metadata()
+                                                                  .clone(),
+                                                       None));
+        metadata.set_description(source_root.get_description().map(|description| {
+            description.to_string()
+        }));
+        let mut dest_parent = dest_parent;
+        let (mut directory, _) = match dest_parent {
+            Some(ref mut dest_parent) => {
+                try!(self.create_with_metadata(metadata, Some(&mut **dest_parent)))
+            }
+            None => try!(self.create_with_metadata(metadata, None)),
+        };
+
+        debug!("Copying {:?} files ...", source_root.get_files().len());
+        for file in source_root.get_files() {
+            directory.upsert_file(try!(File::new(file.get_metadata().clone(),
+                                                 file.get_datamap().clone())));
+        }
+        if !source_root.get_files().is_empty() {
+            let _ = try!(self.update(&directory));
+        }
+
+        for sub_directory_metadata in source_root.get_sub_directories() {
+            let sub_directory = try!(self.get(sub_directory_metadata.get_key()));
+            let _ = try!(self.copy_tree(&sub_directory,
+                                        Some(&mut directory),
+                                        sub_directory_metadata.get_name().clone()));
+        }
+        if let Some(dest_parent) = dest_parent {
+            dest_parent.upsert_sub_directory(directory.get_metadata().clone());
+        }
+        Ok(directory)
+    }
+
     /// Deletes a sub directory
     /// The parent_directory's parent is also updated if present
     /// Returns Option<parent_directory's parent>
@@ -89,19 +252,42 @@ impl DirectoryHelper {
                   parent_directory: &mut DirectoryListing,
                   directory_to_delete: &String)
                   -> Result<Option<DirectoryListing>, NfsError> {
-        try!(parent_directory.remove_sub_directory(directory_to_delete));
+        try!(parent_directory.remove_sub_directory(directory_to_delete)
+                             .map_err(|error| error.with_context(&directory_to_delete[..])));
         parent_directory.get_mut_metadata().set_modified_time(::time::now_utc());
         self.update(&parent_directory)
     }
 
     /// Updates an existing DirectoryListing in the network.
     /// The parent_directory's parent is also updated and the same is returned
+    /// Fails with `NfsError::QuotaExceeded` without updating anything if the directory has a
+    /// quota which the total size of its files exceeds, unless the total size is not larger than
+    /// that of the version in the network. So a directory which is already over its quota, eg.
+    /// after the quota was lowered, can still be updated to delete files or change its metadata
     /// Returns Option<parent_directory's parent>
     pub fn update(&self,
                   directory: &DirectoryListing)
                   -> Result<Option<DirectoryListing>, NfsError> {
+        if let Some(limit) = directory.get_quota() {
+            let used = directory.total_size();
+            if used > limit &&
+               used > try!(self.get_latest(directory.get_key())).total_size() {
+                return Err(NfsError::QuotaExceeded {
+                    used: used,
+                    limit: limit,
+                });
+            }
+        }
         try!(self.update_directory_listing(directory));
         if let Some(parent_dir_key) = directory.get_metadata().get_parent_dir_key() {
+            // Participants other than the owner of the parent can modify a PublicReadWrite
+            // directory, but not its parent
+            if *directory.get_key().get_access_level() == ::AccessLevel::PublicReadWrite &&
+               !try!(self.can_update(&parent_dir_key)) {
+                return Ok(None);
+            }
+            // The parent is updated from its latest version on the network, not a cached one
+            self.remove_cached(&(parent_dir_key.get_id().clone(), None));
             let mut parent_directory = try!(self.get(&parent_dir_key));
             parent_directory.upsert_sub_directory(directory.get_metadata().clone());
             try!(self.update_directory_listing(&parent_directory));
@@ -111,6 +297,32 @@ impl DirectoryHelper {
         }
     }
 
+    /// Updates an existing DirectoryListing in the network and refreshes `directory` with the
+    /// latest DirectoryListing fetched from the network, so that concurrent changes are picked up.
+    /// Returns the refreshed DirectoryListing
+    pub fn update_and_return<'a>(&self,
+                                 directory: &'a mut DirectoryListing)
+                                 -> Result<&'a DirectoryListing, NfsError> {
+        let _ = try!(self.update(directory));
+        let updated_directory = try!(self.get(directory.get_key()));
+        *directory = updated_directory;
+        Ok(directory)
+    }
+
+    /// Sets the human readable description of the directory and updates it in the network.
+    /// The description can be at most MAX_DESCRIPTION_SIZE bytes
+    pub fn set_description(&self,
+                           directory: &mut DirectoryListing,
+                           description: String)
+                           -> Result<(), NfsError> {
+        if description.len() > MAX_DESCRIPTION_SIZE {
+            return Err(NfsError::ParameterIsNotValid);
+        }
+        directory.get_mut_metadata().set_description(Some(description));
+        let _ = try!(self.update(directory));
+        Ok(())
+    }
+
     /// Return the versions of the directory
     pub fn get_versions(&self,
                         directory_id: &XorName,
@@ -127,19 +339,131 @@ impl DirectoryHelper {
                           access_level: &::AccessLevel,
                           version: XorName)
                           -> Result<DirectoryListing, NfsError> {
+        let cache_key = (directory_id.clone(), Some(version));
+        if let Some(directory) = self.get_cached(&cache_key) {
+            return Ok(directory);
+        }
         let immutable_data = try!(self.get_immutable_data(version, ImmutableDataType::Normal));
-        match *access_level {
+        let directory = match *access_level {
             ::AccessLevel::Private => {
-                DirectoryListing::decrypt(self.client.clone(),
-                                          directory_id,
-                                          immutable_data.value().clone())
+                try!(DirectoryListing::decrypt(self.client.clone(),
+                                               directory_id,
+                                               immutable_data.value().clone()))
             }
-            ::AccessLevel::Public => Ok(try!(deserialise(immutable_data.value()))),
+            ::AccessLevel::Public |
+            ::AccessLevel::PublicReadWrite => try!(deserialise(immutable_data.value())),
+        };
+        self.insert_cached(cache_key, directory.clone());
+        Ok(directory)
+    }
+
+    /// Returns the differences between the files of two versions of a directory, eg. as fetched
+    /// using `get_by_version`. Files are matched by name as explained for
+    /// `DirectoryListing::diff_by_name`. Unlike `diff_versions`, nothing is fetched from the
+    /// network
+    pub fn diff_listings(&self,
+                         old_version: &DirectoryListing,
+                         new_version: &DirectoryListing)
+                         -> DirectoryDiff {
+        DirectoryListing::diff_by_name(old_version, new_version)
+    }
+
+    /// Returns the differences between the files of two versions of a versioned directory.
+    /// The versions are identified by their index in the list returned by `get_versions`. If
+    /// `version_a` is greater than `version_b`, the versions are swapped and the diff is marked as
+    /// reversed
+    pub fn diff_versions(&self,
+                         directory: &DirectoryListing,
+                         version_a: usize,
+                         version_b: usize)
+                         -> Result<DirectoryDiff, NfsError> {
+        let directory_key = directory.get_key();
+        if !directory_key.is_versioned() {
+            return Err(NfsError::ParameterIsNotValid);
         }
+        if version_a == version_b {
+            return Ok(DirectoryDiff::default());
+        }
+        let (old_index, new_index, reversed) = if version_a < version_b {
+            (version_a, version_b, false)
+        } else {
+            (version_b, version_a, true)
+        };
+        let versions = try!(self.get_versions(directory_key.get_id(),
+                                              directory_key.get_type_tag()));
+        let old_version = try!(versions.get(old_index).ok_or(NfsError::VersionNotFound));
+        let new_version = try!(versions.get(new_index).ok_or(NfsError::VersionNotFound));
+        debug!("Comparing versions {:?} and {:?} of directory ...",
+               old_index,
+               new_index);
+        let old_directory = try!(self.get_by_version(directory_key.get_id(),
+                                                     directory_key.get_access_level(),
+                                                     *old_version));
+        let new_directory = try!(self.get_by_version(directory_key.get_id(),
+                                                     directory_key.get_access_level(),
+                                                     *new_version));
+        let mut diff = DirectoryListing::diff(&old_directory, &new_directory);
+        diff.reversed = reversed;
+        Ok(diff)
+    }
+
+    /// Removes the versions of a versioned directory which are older than the version at
+    /// `version_index` of the list returned by `get_versions`, making it the oldest version.
+    /// The removed versions are no longer listed, though their data is not deleted from the
+    /// network. Returns the number of versions removed
+    pub fn remove_versions_before(&self,
+                                  directory_key: &DirectoryKey,
+                                  version_index: usize)
+                                  -> Result<usize, NfsError> {
+        if !directory_key.is_versioned() {
+            return Err(NfsError::ParameterIsNotValid);
+        }
+        let structured_data = try!(self.get_structured_data(directory_key.get_id(),
+                                                            directory_key.get_type_tag()));
+        let versions = try!(versioned::get_all_versions(&mut *unwrap_result!(self.client.lock()),
+                                                        &structured_data));
+        if version_index >= versions.len() {
+            return Err(NfsError::VersionNotFound);
+        }
+        if version_index == 0 {
+            return Ok(0);
+        }
+        debug!("Removing {:?} versions of directory ...", version_index);
+        let version_list = try!(serialise(&versions[version_index..].to_vec()));
+        let version_list_name = try!(self.save_as_immutable_data(version_list,
+                                                                 ImmutableDataType::Normal));
+        let (_, signing_key) = try!(self.get_signing_keys(directory_key));
+        let updated_structured_data =
+            try!(StructuredData::new(structured_data.get_type_tag(),
+                                     structured_data.get_identifier().clone(),
+                                     structured_data.get_version() + 1,
+                                     try!(serialise(&version_list_name)),
+                                     structured_data.get_owner_keys().clone(),
+                                     structured_data.get_previous_owner_keys().clone(),
+                                     Some(&signing_key))
+                     .map_err(CoreError::from));
+        try!(unwrap_result!(self.client.lock())
+                 .post(Data::Structured(updated_structured_data), None));
+        Ok(version_index)
     }
 
-    /// Return the DirectoryListing for the latest version
+    /// Return the DirectoryListing for the latest version. If the cache is enabled, a cached
+    /// latest version is returned, otherwise it is fetched as explained for `get_latest`
     pub fn get(&self, directory_key: &DirectoryKey) -> Result<DirectoryListing, NfsError> {
+        let cache_key = (directory_key.get_id().clone(), None);
+        if let Some(directory) = self.get_cached(&cache_key) {
+            return Ok(directory);
+        }
+        let directory = try!(self.get_latest(directory_key));
+        self.insert_cached(cache_key, directory.clone());
+        Ok(directory)
+    }
+
+    /// Return the DirectoryListing for the latest version from the network, without looking it up
+    /// in the cache. An unversioned directory is decoded directly from its StructuredData, without
+    /// enumerating versions, while a versioned directory is fetched through `get_by_version` with
+    /// its head version
+    pub fn get_latest(&self, directory_key: &DirectoryKey) -> Result<DirectoryListing, NfsError> {
         let directory_id = directory_key.get_id();
         let type_tag = directory_key.get_type_tag();
         let versioned = directory_key.is_versioned();
@@ -168,7 +492,7 @@ impl DirectoryHelper {
 
                     Some((&private_key, &secret_key, &nonce))
                 }
-                ::AccessLevel::Public => None,
+                ::AccessLevel::Public | ::AccessLevel::PublicReadWrite => None,
             };
 
             let structured_data = try!(self.get_structured_data(directory_id, type_tag));
@@ -181,19 +505,27 @@ impl DirectoryHelper {
 
     /// Returns the Root Directory
     pub fn get_user_root_directory_listing(&self) -> Result<DirectoryListing, NfsError> {
+        Ok(try!(self.create_root_if_absent()).0)
+    }
+
+    /// Returns the Root Directory, creating it if the user does not have one yet.
+    /// The flag is true if the Root Directory was created by this call, so that any first time
+    /// setup can be performed only then
+    pub fn create_root_if_absent(&self) -> Result<(DirectoryListing, bool), NfsError> {
         let root_directory_id = unwrap_result!(self.client.lock())
                                     .get_user_root_directory_id()
                                     .map(|id| id.clone());
         match root_directory_id {
             Some(id) => {
                 debug!("Retrieving directory at id {:?} ...", id);
-                self.get(&DirectoryKey::new(id,
-                                            ::UNVERSIONED_DIRECTORY_LISTING_TAG,
-                                            false,
-                                            ::AccessLevel::Private))
+                Ok((try!(self.get(&DirectoryKey::new(id,
+                                                     ::UNVERSIONED_DIRECTORY_LISTING_TAG,
+                                                     false,
+                                                     ::AccessLevel::Private))),
+                    false))
             }
             None => {
-                debug!("Retrieving root directory ...");
+                debug!("Creating root directory ...");
                 let (created_directory, _) = try!(self.create(::ROOT_DIRECTORY_NAME.to_string(),
                                                               ::UNVERSIONED_DIRECTORY_LISTING_TAG,
                                                               Vec::new(),
@@ -202,7 +534,7 @@ impl DirectoryHelper {
                                                               None));
                 try!(unwrap_result!(self.client.lock())
                          .set_user_root_directory_id(created_directory.get_key().get_id().clone()));
-                Ok(created_directory)
+                Ok((created_directory, true))
             }
         }
     }
@@ -262,20 +594,435 @@ impl DirectoryHelper {
         }
     }
 
+    /// Returns the number of files in the directory tree rooted at `root`
+    pub fn count_all(&self, root: &DirectoryListing) -> Result<u64, NfsError> {
+        Ok(try!(self.count_tree(root)).0)
+    }
+
+    /// Returns the total size of the files in the directory tree rooted at `root`, grouped by
+    /// content type. The MIME type of a file is used if set, else the extension of its name.
+    /// Files with neither are grouped under "_unknown"
+    pub fn get_size_breakdown(&self,
+                              root: &DirectoryListing)
+                              -> Result<HashMap<String, u64>, NfsError> {
+        let mut breakdown = HashMap::new();
+        try!(self.visit_tree(root,
+                             &mut |directory: &DirectoryListing| {
+                                 for file in directory.get_files() {
+                                     let key = match file.get_metadata().get_mime_type() {
+                                         Some(mime_type) => mime_type.to_string(),
+                                         None => {
+                                             file.get_extension()
+                                                 .map(|extension| extension.to_lowercase())
+                                                 .unwrap_or("_unknown".to_string())
+                                         }
+                                     };
+                                     *breakdown.entry(key).or_insert(0) += file.get_size();
+                                 }
+                             }));
+        Ok(breakdown)
+    }
+
+    /// Fetches the latest versions of the DirectoryListings for `keys` concurrently, using up to
+    /// BATCH_FETCH_THREADS threads. The results are returned in the same order as `keys`
+    pub fn get_batch(&self, keys: &[DirectoryKey]) -> Vec<Result<DirectoryListing, NfsError>> {
+        debug!("Fetching {:?} directories concurrently ...", keys.len());
+        let keys = Arc::new(keys.to_vec());
+        // Index of the next key to be fetched by any of the threads
+        let next_index = Arc::new(AtomicUsize::new(0));
+        let handles: Vec<_> = (0..cmp::min(keys.len(), BATCH_FETCH_THREADS))
+                                  .map(|_| {
+                                      let client = self.client.clone();
+                                      let keys = keys.clone();
+                                      let next_index = next_index.clone();
+                                      thread::spawn(move || {
+                                          let directory_helper = DirectoryHelper::new(client);
+                                          let mut results = Vec::new();
+                                          loop {
+                                              let index = next_index.fetch_add(1,
+                                                                               Ordering::SeqCst);
+                                              if index >= keys.len() {
+                                                  return results;
+                                              }
+                                              results.push((index,
+                                                            directory_helper.get(&keys[index])));
+                                          }
+                                      })
+                                  })
+                                  .collect();
+
+        let mut results: Vec<Option<Result<DirectoryListing, NfsError>>> =
+            keys.iter().map(|_| None).collect();
+        for handle in handles {
+            // The keys taken by a thread which panicked are left without a result
+            if let Ok(thread_results) = handle.join() {
+                for (index, result) in thread_results {
+                    results[index] = Some(result);
+                }
+            }
+        }
+        results.into_iter()
+               .map(|result| {
+                   result.unwrap_or_else(|| {
+                       Err(NfsError::Unexpected("Fetch thread panicked".to_string()))
+                   })
+               })
+               .collect()
+    }
+
+    /// Fetches the latest versions of the DirectoryListings for `keys` concurrently, failing if
+    /// any of them could not be fetched. The listings are returned in the same order as `keys`
+    pub fn get_batch_all_ok(&self,
+                            keys: &[DirectoryKey])
+                            -> Result<Vec<DirectoryListing>, NfsError> {
+        self.get_batch(keys).into_iter().collect()
+    }
+
+    /// Returns a printable ASCII tree of the directory tree rooted at `root`, fetching the
+    /// sub-directories up to a depth of `max_depth`. Sub-directories which could not be fetched
+    /// are shown without their contents
+    pub fn get_tree_string(&self, root: &DirectoryListing, max_depth: usize) -> String {
+        root.to_tree_string_with(max_depth,
+                                 &mut |metadata: &DirectoryMetadata| {
+                                     self.get(metadata.get_key()).ok()
+                                 })
+    }
+
+    /// Searches the directory tree rooted at `root` breadth first for a file named `name`.
+    /// Returns the first match along with the names of the directories leading from `root` to the
+    /// directory containing the file
+    pub fn find_file_anywhere(&self,
+                              root: &DirectoryListing,
+                              name: &str)
+                              -> Result<Option<(Vec<String>, File)>, NfsError> {
+        Ok(try!(self.find_files_named(root, name, true)).pop())
+    }
+
+    /// Searches the whole directory tree rooted at `root` for files named `name`. Returns all the
+    /// matches, in breadth first order, along with the names of the directories leading from
+    /// `root` to the directory containing each file
+    pub fn find_all_files_named(&self,
+                                root: &DirectoryListing,
+                                name: &str)
+                                -> Result<Vec<(Vec<String>, File)>, NfsError> {
+        self.find_files_named(root, name, false)
+    }
+
+    /// Invokes `visitor` for each file in the directory tree rooted at `root`, in pre-order: the
+    /// files of a directory are visited before its sub-directories. A sub-directory is fetched
+    /// only when it is reached, so returning `WalkControl::SkipDirectory` avoids fetching the
+    /// sub-directories of the current directory. Use `walk_with_directories` to skip directories
+    /// which hold no files
+    pub fn walk<F>(&self, root: &DirectoryListing, visitor: F) -> Result<(), NfsError>
+        where F: FnMut(&DirectoryListing, &File) -> WalkControl
+    {
+        self.walk_with_directories(root, visitor, |_, _| WalkControl::Continue)
+    }
+
+    /// Same as `walk`, additionally invoking `directory_visitor` with the parent DirectoryListing
+    /// and the metadata of each sub-directory before it is fetched. Returning
+    /// `WalkControl::SkipDirectory` from `directory_visitor` skips the sub-directory without
+    /// fetching it, and `WalkControl::Stop` ends the traversal
+    pub fn walk_with_directories<F, D>(&self,
+                                       root: &DirectoryListing,
+                                       file_visitor: F,
+                                       directory_visitor: D)
+                                       -> Result<(), NfsError>
+        where F: FnMut(&DirectoryListing, &File) -> WalkControl,
+              D: FnMut(&DirectoryListing, &DirectoryMetadata) -> WalkControl
+    {
+        let mut file_visitor = file_visitor;
+        let mut directory_visitor = directory_visitor;
+        let mut path_file_visitor = |_: &[String], directory: &DirectoryListing, file: &File| {
+            file_visitor(directory, file)
+        };
+        let mut path_directory_visitor = |_: &[String],
+                                          directory: &DirectoryListing,
+                                          metadata: &DirectoryMetadata| {
+            directory_visitor(directory, metadata)
+        };
+        let _ = try!(self.walk_directory(root,
+                                         &mut Vec::new(),
+                                         &mut path_file_visitor,
+                                         &mut path_directory_visitor));
+        Ok(())
+    }
+
+    /// Returns all the files in the directory tree rooted at `root`, in the order explained for
+    /// `walk`, along with the names of the directories leading from `root` to the directory
+    /// containing each file
+    pub fn list_all_files(&self,
+                          root: &DirectoryListing)
+                          -> Result<Vec<(Vec<String>, File)>, NfsError> {
+        let mut files = Vec::new();
+        {
+            let mut visitor = |path: &[String], _: &DirectoryListing, file: &File| {
+                files.push((path.to_vec(), file.clone()));
+                WalkControl::Continue
+            };
+            let _ = try!(self.walk_directory(root,
+                                             &mut Vec::new(),
+                                             &mut visitor,
+                                             &mut |_: &[String],
+                                                   _: &DirectoryListing,
+                                                   _: &DirectoryMetadata| {
+                                                 WalkControl::Continue
+                                             }));
+        }
+        Ok(files)
+    }
+
+    /// Exports the directory tree rooted at `root` to the local directory `dest`, creating it if
+    /// required. Sub-directories are recreated beneath `dest` and the content of each file is
+    /// written to a local file of the same name. Existing local files are overwritten
+    pub fn export_tree(&self,
+                       root: &DirectoryListing,
+                       dest: &Path)
+                       -> Result<ExportStats, NfsError> {
+        let mut stats = ExportStats::default();
+        try!(fs::create_dir_all(dest));
+        try!(self.export_directory(root, dest, "", &mut stats));
+        Ok(stats)
+    }
+
+    /// Imports the local directory `source` along with its contents into a new directory of the
+    /// same name. If `parent` is passed, the new directory is created in it and inherits its type
+    /// tag, versioning and AccessLevel, else a versioned private directory is created. Files are
+    /// uploaded with a MIME type inferred from their extension.
+    /// Returns (created_directory, stats)
+    pub fn import_tree(&self,
+                       source: &Path,
+                       parent: Option<DirectoryListing>)
+                       -> Result<(DirectoryListing, ImportStats), NfsError> {
+        let name = try!(source.file_name()
+                              .and_then(|name| name.to_str())
+                              .ok_or(NfsError::ParameterIsNotValid));
+        let mut parent = parent;
+        let (tag_type, versioned, access_level) = match parent {
+            Some(ref parent) => {
+                let key = parent.get_key();
+                (key.get_type_tag(), key.is_versioned(), key.get_access_level().clone())
+            }
+            None => (::VERSIONED_DIRECTORY_LISTING_TAG, true, ::AccessLevel::Private),
+        };
+        let (directory, _) = try!(self.create(name.to_string(),
+                                              tag_type,
+                                              Vec::new(),
+                                              versioned,
+                                              access_level,
+                                              parent.as_mut()));
+        let mut stats = ImportStats::default();
+        let directory = try!(self.import_directory(source, directory, &mut stats));
+        Ok((directory, stats))
+    }
+
+    /// Returns the number of directories in the directory tree rooted at `root`, including `root`
+    pub fn total_directory_count(&self, root: &DirectoryListing) -> Result<u64, NfsError> {
+        Ok(try!(self.count_tree(root)).1)
+    }
+
+    /// Returns (number of files, number of directories) in the tree rooted at `root`
+    fn count_tree(&self, root: &DirectoryListing) -> Result<(u64, u64), NfsError> {
+        let mut file_count = 0u64;
+        let mut directory_count = 0u64;
+        try!(self.visit_tree(root,
+                             &mut |directory: &DirectoryListing| {
+                                 file_count += directory.get_files().len() as u64;
+                                 directory_count += 1;
+                             }));
+        Ok((file_count, directory_count))
+    }
+
+    fn find_files_named(&self,
+                        root: &DirectoryListing,
+                        name: &str,
+                        first_only: bool)
+                        -> Result<Vec<(Vec<String>, File)>, NfsError> {
+        let mut matches = Vec::new();
+        let mut pending = VecDeque::new();
+        pending.push_back((Vec::new(), root.clone()));
+        while let Some((path, directory)) = pending.pop_front() {
+            if let Some(file) = directory.find_file(&name.to_string()) {
+                matches.push((path.clone(), file.clone()));
+                if first_only {
+                    break;
+                }
+            }
+            for metadata in directory.get_sub_directories() {
+                let mut sub_directory_path = path.clone();
+                sub_directory_path.push(metadata.get_name().clone());
+                pending.push_back((sub_directory_path, try!(self.get(metadata.get_key()))));
+            }
+        }
+        Ok(matches)
+    }
+
+    /// Walks the tree rooted at `directory` as explained for `walk_with_directories`, additionally
+    /// passing the visitors the names of the directories leading to `directory`, which are held
+    /// in `path`.
+    /// Returns false if the traversal was stopped
+    fn walk_directory<F, D>(&self,
+                            directory: &DirectoryListing,
+                            path: &mut Vec<String>,
+                            file_visitor: &mut F,
+                            directory_visitor: &mut D)
+                            -> Result<bool, NfsError>
+        where F: FnMut(&[String], &DirectoryListing, &File) -> WalkControl,
+              D: FnMut(&[String], &DirectoryListing, &DirectoryMetadata) -> WalkControl
+    {
+        for file in directory.get_files() {
+            match file_visitor(path, directory, file) {
+                WalkControl::Continue => (),
+                WalkControl::SkipDirectory => return Ok(true),
+                WalkControl::Stop => return Ok(false),
+            }
+        }
+        for metadata in directory.get_sub_directories() {
+            match directory_visitor(path, directory, metadata) {
+                WalkControl::Continue => (),
+                WalkControl::SkipDirectory => continue,
+                WalkControl::Stop => return Ok(false),
+            }
+            let sub_directory = try!(self.get(metadata.get_key()));
+            path.push(metadata.get_name().clone());
+            let completed = try!(self.walk_directory(&sub_directory,
+                                                     path,
+                                                     file_visitor,
+                                                     directory_visitor));
+            let _ = path.pop();
+            if !completed {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Exports the contents of `directory` into the existing local directory `dest`. `path` is
+    /// the path of `directory` relative to the exported root, used to report skipped entries
+    fn export_directory(&self,
+                        directory: &DirectoryListing,
+                        dest: &Path,
+                        path: &str,
+                        stats: &mut ExportStats)
+                        -> Result<(), NfsError> {
+        for file in directory.get_files() {
+            if !is_valid_local_name(file.get_name()) {
+                stats.skipped.push(format!("{}{}", path, file.get_name()));
+                continue;
+            }
+            let mut reader = Reader::new(self.client.clone(), file);
+            let size = reader.size();
+            let content = try!(reader.read(0, size));
+            debug!("Exporting file {:?} ...", file.get_name());
+            let mut local_file = try!(fs::File::create(dest.join(file.get_name())));
+            try!(local_file.write_all(&content));
+            stats.total_files += 1;
+            stats.total_bytes += size;
+        }
+        for metadata in directory.get_sub_directories() {
+            let sub_directory_path = format!("{}{}/", path, metadata.get_name());
+            if !is_valid_local_name(metadata.get_name()) {
+                stats.skipped.push(sub_directory_path);
+                continue;
+            }
+            let sub_directory_dest = dest.join(metadata.get_name());
+            try!(fs::create_dir_all(&sub_directory_dest));
+            let sub_directory = try!(self.get(metadata.get_key()));
+            try!(self.export_directory(&sub_directory,
+                                       &sub_directory_dest,
+                                       &sub_directory_path,
+                                       stats));
+        }
+        Ok(())
+    }
+
+    /// Uploads the files of the local directory `source` into `directory` and recursively imports
+    /// its sub-directories. Returns the updated `directory`
+    fn import_directory(&self,
+                        source: &Path,
+                        directory: DirectoryListing,
+                        stats: &mut ImportStats)
+                        -> Result<DirectoryListing, NfsError> {
+        let mut directory = directory;
+        let mut entries = Vec::new();
+        for entry in try!(fs::read_dir(source)) {
+            let path = try!(entry).path();
+            match path.file_name().and_then(|name| name.to_str()).map(|name| name.to_string()) {
+                Some(name) => entries.push((name, path.clone())),
+                None => stats.failed.push(path.to_string_lossy().into_owned()),
+            }
+        }
+        entries.sort();
+
+        let file_helper = FileHelper::new(self.client.clone());
+        let mut sub_directories = Vec::new();
+        for (name, path) in entries {
+            if path.is_dir() {
+                sub_directories.push((name, path));
+                continue;
+            }
+            let mut content = Vec::new();
+            if fs::File::open(&path).and_then(|mut file| file.read_to_end(&mut content)).is_err() {
+                stats.failed.push(path.to_string_lossy().into_owned());
+                continue;
+            }
+            debug!("Importing file {:?} ...", name);
+            let mime_type = path.extension()
+                                .and_then(|extension| extension.to_str())
+                                .and_then(utils::mime_type_from_extension)
+                                .map(|mime_type| mime_type.to_string());
+            let mut writer = try!(file_helper.create_with_mime_type(name,
+                                                                    Vec::new(),
+                                                                    mime_type,
+                                                                    directory));
+            writer.write(&content, 0);
+            directory = try!(writer.close()).0;
+            stats.files_uploaded += 1;
+            stats.bytes_transferred += content.len() as u64;
+        }
+
+        for (name, path) in sub_directories {
+            let key = directory.get_key().clone();
+            let (sub_directory, _) = try!(self.create(name,
+                                                      key.get_type_tag(),
+                                                      Vec::new(),
+                                                      key.is_versioned(),
+                                                      key.get_access_level().clone(),
+                                                      Some(&mut directory)));
+            let _ = try!(self.import_directory(&path, sub_directory, stats));
+            // Importing the sub-directory updates this directory in the network
+            directory = try!(self.get(&key));
+        }
+        Ok(directory)
+    }
+
+    /// Invokes `visitor` for `directory` and then recursively for each of its sub-directories
+    fn visit_tree<F>(&self, directory: &DirectoryListing, visitor: &mut F) -> Result<(), NfsError>
+        where F: FnMut(&DirectoryListing)
+    {
+        visitor(directory);
+        for metadata in directory.get_sub_directories() {
+            let sub_directory = try!(self.get(metadata.get_key()));
+            try!(self.visit_tree(&sub_directory, visitor));
+        }
+        Ok(())
+    }
+
     /// Creates a StructuredData in the Network
     /// The StructuredData is created based on the version and AccessLevel of the DirectoryListing
     fn save_directory_listing(&self,
                               directory: &DirectoryListing)
                               -> Result<StructuredData, NfsError> {
-        let signing_key = try!(unwrap_result!(self.client.lock()).get_secret_signing_key()).clone();
-        let owner_key = try!(unwrap_result!(self.client.lock()).get_public_signing_key()).clone();
+        let (owner_key, signing_key) = try!(self.get_signing_keys(directory.get_key()));
         let access_level = directory.get_key().get_access_level();
         let versioned = directory.get_key().is_versioned();
 
         if versioned {
             let serialised_data = match *access_level {
                 ::AccessLevel::Private => try!(directory.encrypt(self.client.clone())),
-                ::AccessLevel::Public => try!(serialise(&directory)),
+                ::AccessLevel::Public | ::AccessLevel::PublicReadWrite => {
+                    try!(serialise(&directory))
+                }
             };
             let version = try!(self.save_as_immutable_data(serialised_data,
                                                            ImmutableDataType::Normal));
@@ -297,7 +1044,7 @@ impl DirectoryHelper {
 
             let encryption_keys = match *access_level {
                 ::AccessLevel::Private => Some((&private_key, &secret_key, &nonce)),
-                ::AccessLevel::Public => None,
+                ::AccessLevel::Public | ::AccessLevel::PublicReadWrite => None,
             };
             Ok(try!(unversioned::create(self.client.clone(),
                                         directory.get_key().get_type_tag(),
@@ -312,18 +1059,20 @@ impl DirectoryHelper {
     }
 
     fn update_directory_listing(&self, directory: &DirectoryListing) -> Result<(), NfsError> {
+        self.remove_cached(&(directory.get_key().get_id().clone(), None));
         let structured_data = try!(self.get_structured_data(directory.get_key().get_id(),
                                                             directory.get_key().get_type_tag()));
 
-        let signing_key = try!(unwrap_result!(self.client.lock()).get_secret_signing_key()).clone();
-        let owner_key = try!(unwrap_result!(self.client.lock()).get_public_signing_key()).clone();
+        let (owner_key, signing_key) = try!(self.get_signing_keys(directory.get_key()));
         let access_level = directory.get_key().get_access_level();
         let versioned = directory.get_key().is_versioned();
 
         let updated_structured_data = if versioned {
             let serialised_data = match *access_level {
                 ::AccessLevel::Private => try!(directory.encrypt(self.client.clone())),
-                ::AccessLevel::Public => try!(serialise(&directory)),
+                ::AccessLevel::Public | ::AccessLevel::PublicReadWrite => {
+                    try!(serialise(&directory))
+                }
             };
             let version = try!(self.save_as_immutable_data(serialised_data,
                                                            ImmutableDataType::Normal));
@@ -341,7 +1090,7 @@ impl DirectoryHelper {
 
             let encryption_keys = match *access_level {
                 ::AccessLevel::Private => Some((&private_key, &secret_key, &nonce)),
-                ::AccessLevel::Public => None,
+                ::AccessLevel::Public | ::AccessLevel::PublicReadWrite => None,
             };
             try!(unversioned::create(self.client.clone(),
                                      directory.get_key().get_type_tag(),
@@ -372,6 +1121,34 @@ impl DirectoryHelper {
     }
 
     /// Get StructuredData from the Network
+    /// Returns the keys with which the StructuredData of the directory is owned and signed. A
+    /// PublicReadWrite directory is owned by a key pair derived from its id, so that any network
+    /// participant who knows its DirectoryKey can modify it. Other directories are owned by the
+    /// client
+    fn get_signing_keys(&self,
+                        directory_key: &DirectoryKey)
+                        -> Result<(sign::PublicKey, sign::SecretKey), NfsError> {
+        match *directory_key.get_access_level() {
+            ::AccessLevel::PublicReadWrite => {
+                let seed = sign::Seed(sha256::hash(&directory_key.get_id().0).0);
+                Ok(sign::keypair_from_seed(&seed))
+            }
+            ::AccessLevel::Private | ::AccessLevel::Public => {
+                let client = unwrap_result!(self.client.lock());
+                Ok((try!(client.get_public_signing_key()).clone(),
+                    try!(client.get_secret_signing_key()).clone()))
+            }
+        }
+    }
+
+    /// Returns true if the client can sign updates of the directory with key `directory_key`
+    fn can_update(&self, directory_key: &DirectoryKey) -> Result<bool, NfsError> {
+        let (owner_key, _) = try!(self.get_signing_keys(directory_key));
+        let structured_data = try!(self.get_structured_data(directory_key.get_id(),
+                                                            directory_key.get_type_tag()));
+        Ok(structured_data.get_owner_keys().contains(&owner_key))
+    }
+
     fn get_structured_data(&self, id: &XorName, type_tag: u64) -> Result<StructuredData, NfsError> {
         let request = DataRequest::Structured(id.clone(), type_tag);
         debug!("Getting structured data from the network ...");
@@ -382,6 +1159,22 @@ impl DirectoryHelper {
         }
     }
 
+    fn get_cached(&self, key: &CacheKey) -> Option<DirectoryListing> {
+        self.cache.as_ref().and_then(|cache| unwrap_result!(cache.lock()).get(key))
+    }
+
+    fn insert_cached(&self, key: CacheKey, directory: DirectoryListing) {
+        if let Some(ref cache) = self.cache {
+            unwrap_result!(cache.lock()).insert(key, directory);
+        }
+    }
+
+    fn remove_cached(&self, key: &CacheKey) {
+        if let Some(ref cache) = self.cache {
+            unwrap_result!(cache.lock()).remove(key);
+        }
+    }
+
     /// Get ImmutableData from the Network
     fn get_immutable_data(&self,
                           id: XorName,
@@ -397,11 +1190,73 @@ impl DirectoryHelper {
     }
 }
 
+/// Id of a directory along with the version of the DirectoryListing. None for the latest version
+type CacheKey = (XorName, Option<XorName>);
+
+/// Least recently used cache of DirectoryListings
+struct DirectoryCache {
+    capacity: usize,
+    // Ordered from the least to the most recently used
+    entries: VecDeque<(CacheKey, DirectoryListing)>,
+}
+
+impl DirectoryCache {
+    fn new(capacity: usize) -> DirectoryCache {
+        DirectoryCache {
+            capacity: capacity,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    fn get(&mut self, key: &CacheKey) -> Option<DirectoryListing> {
+        let position = self.entries.iter().position(|entry| entry.0 == *key);
+        let entry = match position.and_then(|index| self.entries.remove(index)) {
+            Some(entry) => entry,
+            None => return None,
+        };
+        let directory = entry.1.clone();
+        self.entries.push_back(entry);
+        Some(directory)
+    }
+
+    fn insert(&mut self, key: CacheKey, directory: DirectoryListing) {
+        self.remove(&key);
+        if self.entries.len() == self.capacity {
+            let _ = self.entries.pop_front();
+        }
+        self.entries.push_back((key, directory));
+    }
+
+    fn remove(&mut self, key: &CacheKey) {
+        let position = self.entries.iter().position(|entry| entry.0 == *key);
+        if let Some(index) = position {
+            let _ = self.entries.remove(index);
+        }
+    }
+}
+
+/// Returns true if `name` can be used as a single component of a local path
+fn is_valid_local_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains('/') &&
+    !name.contains('\\') && !name.contains('\0')
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use std::env;
+    use std::fs;
+    use std::io::{Read, Write};
+    use std::path::PathBuf;
     use std::sync::{Arc, Mutex};
-    use safe_core::utility::test_utils;
+    use file::File;
+    use metadata::directory_key::DirectoryKey;
+    use metadata::directory_metadata::DirectoryMetadata;
+    use metadata::file_metadata::FileMetadata;
+    use self_encryption::DataMap;
+    use safe_core::utility::{self, test_utils};
+    use utils::test::generate_random_file_name;
+    use xor_name::XorName;
 
     #[test]
     fn create_dir_listing() {
@@ -454,6 +1309,37 @@ mod test {
                    *grand_child_directory.get_metadata().get_modified_time());
     }
 
+    #[test]
+    fn create_dir_listing_with_metadata() {
+        let test_client = unwrap_result!(test_utils::get_client());
+        let client = Arc::new(Mutex::new(test_client));
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut directory, _) = unwrap_result!(dir_helper.create("DirName".to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                None));
+        let metadata = unwrap_result!(DirectoryMetadata::new("Child".to_string(),
+                                                             ::UNVERSIONED_DIRECTORY_LISTING_TAG,
+                                                             false,
+                                                             ::AccessLevel::Public,
+                                                             vec![1u8; 10],
+                                                             None));
+        let (child_directory, _) =
+            unwrap_result!(dir_helper.create_with_metadata(metadata.clone(),
+                                                           Some(&mut directory)));
+        assert_eq!(*child_directory.get_metadata().get_user_metadata(), vec![1u8; 10]);
+        assert_eq!(*child_directory.get_key().get_access_level(),
+                   ::AccessLevel::Public);
+        assert_eq!(*unwrap_option!(child_directory.get_metadata().get_parent_dir_key(),
+                                   "Parent directory key should be set"),
+                   *directory.get_key());
+        assert_eq!(child_directory,
+                   unwrap_result!(dir_helper.get(child_directory.get_key())));
+        assert!(dir_helper.create_with_metadata(metadata, Some(&mut directory)).is_err());
+    }
+
     #[test]
     fn create_versioned_public_directory() {
         let public_directory;
@@ -504,6 +1390,45 @@ mod test {
         }
     }
 
+    #[test]
+    fn public_read_write_directory_shared_between_clients() {
+        let first_client = Arc::new(Mutex::new(unwrap_result!(test_utils::get_client())));
+        let first_dir_helper = DirectoryHelper::new(first_client.clone());
+        let mut root_dir = unwrap_result!(first_dir_helper.get_user_root_directory_listing());
+        let (directory, _) =
+            unwrap_result!(first_dir_helper.create("SharedDirectory".to_string(),
+                                                   ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                   Vec::new(),
+                                                   true,
+                                                   ::AccessLevel::PublicReadWrite,
+                                                   Some(&mut root_dir)));
+        assert_eq!(*directory.get_key().get_access_level(),
+                   ::AccessLevel::PublicReadWrite);
+
+        // A second client adds a file to the directory
+        {
+            let second_client = Arc::new(Mutex::new(unwrap_result!(test_utils::get_client())));
+            let second_dir_helper = DirectoryHelper::new(second_client.clone());
+            let shared_directory = unwrap_result!(second_dir_helper.get(directory.get_key()));
+            let file_helper = ::helper::file_helper::FileHelper::new(second_client.clone());
+            let mut writer = unwrap_result!(file_helper.create("shared.txt".to_string(),
+                                                               Vec::new(),
+                                                               shared_directory));
+            writer.write(&[1u8, 2, 3], 0);
+            let (_, updated_parent) = unwrap_result!(writer.close());
+            assert!(updated_parent.is_none());
+        }
+
+        // The first client reads it
+        let retrieved_directory = unwrap_result!(first_dir_helper.get(directory.get_key()));
+        let file = unwrap_option!(retrieved_directory.find_file(&"shared.txt".to_string()),
+                                  "File not found");
+        let file_helper = ::helper::file_helper::FileHelper::new(first_client.clone());
+        let mut reader = file_helper.read(file);
+        let size = reader.size();
+        assert_eq!(unwrap_result!(reader.read(0, size)), vec![1u8, 2, 3]);
+    }
+
     #[test]
     fn user_root_configuration() {
         let test_client = unwrap_result!(test_utils::get_client());
@@ -521,6 +1446,21 @@ mod test {
         assert!(root_dir.find_sub_directory(created_dir.get_metadata().get_name()).is_some());
     }
 
+    #[test]
+    fn create_root_if_absent() {
+        let test_client = unwrap_result!(test_utils::get_client());
+        let client = Arc::new(Mutex::new(test_client));
+        let dir_helper = DirectoryHelper::new(client.clone());
+
+        let (created_root, created) = unwrap_result!(dir_helper.create_root_if_absent());
+        assert!(created);
+        let (existing_root, created) = unwrap_result!(dir_helper.create_root_if_absent());
+        assert!(!created);
+        assert_eq!(existing_root, created_root);
+        assert_eq!(unwrap_result!(dir_helper.get_user_root_directory_listing()),
+                   created_root);
+    }
+
     #[test]
     fn configuration_directory() {
         let test_client = unwrap_result!(test_utils::get_client());
@@ -575,6 +1515,512 @@ mod test {
                    "DirName2".to_string());
     }
 
+    #[test]
+    fn diff_directory_versions() {
+        let test_client = unwrap_result!(test_utils::get_client());
+        let client = Arc::new(Mutex::new(test_client));
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut directory, _) = unwrap_result!(dir_helper.create("DirName".to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                None));
+        let file = unwrap_result!(File::new(FileMetadata::new("hello.txt".to_string(),
+                                                              Vec::new()),
+                                            DataMap::None));
+        directory.upsert_file(file.clone());
+        let _ = unwrap_result!(dir_helper.update(&directory));
+
+        let diff = unwrap_result!(dir_helper.diff_versions(&directory, 0, 1));
+        assert_eq!(diff.added, vec![file.clone()]);
+        assert!(diff.removed.is_empty());
+        assert!(!diff.reversed);
+
+        let diff = unwrap_result!(dir_helper.diff_versions(&directory, 1, 0));
+        assert_eq!(diff.added, vec![file]);
+        assert!(diff.reversed);
+
+        assert!(unwrap_result!(dir_helper.diff_versions(&directory, 1, 1)).is_empty());
+        assert!(dir_helper.diff_versions(&directory, 0, 2).is_err());
+    }
+
+    #[test]
+    fn diff_directory_versions_with_modified_file() {
+        let test_client = unwrap_result!(test_utils::get_client());
+        let client = Arc::new(Mutex::new(test_client));
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let new_file = |name: &str| {
+            unwrap_result!(File::new(FileMetadata::new(name.to_string(), Vec::new()),
+                                     DataMap::None))
+        };
+        let (mut directory, _) = unwrap_result!(dir_helper.create("DirName".to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                None));
+        let existing_file = new_file("existing.txt");
+        directory.upsert_file(existing_file.clone());
+        let _ = unwrap_result!(dir_helper.update(&directory));
+
+        // Add a file
+        let added_file = new_file("added.txt");
+        directory.upsert_file(added_file.clone());
+        let _ = unwrap_result!(dir_helper.update(&directory));
+
+        // Modify the existing file
+        let mut modified_file = existing_file.clone();
+        modified_file.get_mut_metadata().set_user_metadata(vec![1u8; 10]);
+        directory.upsert_file(modified_file.clone());
+        let _ = unwrap_result!(dir_helper.update(&directory));
+
+        let diff = unwrap_result!(dir_helper.diff_versions(&directory, 1, 3));
+        assert_eq!(diff.added, vec![added_file.clone()]);
+        assert_eq!(diff.modified,
+                   vec![(existing_file.clone(), modified_file.clone())]);
+        assert!(diff.removed.is_empty());
+
+        let key = directory.get_key();
+        let versions = unwrap_result!(dir_helper.get_versions(key.get_id(), key.get_type_tag()));
+        let version_1 = unwrap_result!(dir_helper.get_by_version(key.get_id(),
+                                                                 key.get_access_level(),
+                                                                 versions[1]));
+        let version_3 = unwrap_result!(dir_helper.get_by_version(key.get_id(),
+                                                                 key.get_access_level(),
+                                                                 versions[3]));
+        let diff = dir_helper.diff_listings(&version_1, &version_3);
+        assert_eq!(diff.added, vec![added_file]);
+        assert_eq!(diff.modified, vec![(existing_file, modified_file.clone())]);
+        assert!(diff.removed.is_empty());
+
+        // Files are matched by name, so a renamed file is removed and added
+        let mut renamed_file = modified_file.clone();
+        renamed_file.get_mut_metadata().set_name("renamed.txt".to_string());
+        let mut renamed_directory = version_3.clone();
+        renamed_directory.upsert_file(renamed_file.clone());
+        let diff = dir_helper.diff_listings(&version_3, &renamed_directory);
+        assert_eq!(diff.added, vec![renamed_file]);
+        assert_eq!(diff.removed, vec![modified_file]);
+        assert!(diff.modified.is_empty());
+    }
+
+    #[test]
+    fn count_files_and_directories() {
+        let test_client = unwrap_result!(test_utils::get_client());
+        let client = Arc::new(Mutex::new(test_client));
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut directory, _) = unwrap_result!(dir_helper.create("DirName".to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                None));
+        assert_eq!(unwrap_result!(dir_helper.total_directory_count(&directory)), 1);
+        assert_eq!(unwrap_result!(dir_helper.count_all(&directory)), 0);
+
+        let (mut child_directory, _) =
+            unwrap_result!(dir_helper.create("Child".to_string(),
+                                             ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                             Vec::new(),
+                                             true,
+                                             ::AccessLevel::Private,
+                                             Some(&mut directory)));
+        let _ = unwrap_result!(dir_helper.create("Grand Child".to_string(),
+                                                 ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                 Vec::new(),
+                                                 true,
+                                                 ::AccessLevel::Private,
+                                                 Some(&mut child_directory)));
+        for name in &["a.txt", "b.txt"] {
+            let file = unwrap_result!(File::new(FileMetadata::new(name.to_string(), Vec::new()),
+                                                DataMap::None));
+            child_directory.upsert_file(file);
+        }
+        let _ = unwrap_result!(dir_helper.update(&child_directory));
+
+        let directory = unwrap_result!(dir_helper.get(directory.get_key()));
+        assert_eq!(unwrap_result!(dir_helper.total_directory_count(&directory)), 3);
+        assert_eq!(unwrap_result!(dir_helper.count_all(&directory)), 2);
+    }
+
+    #[test]
+    fn size_breakdown() {
+        let test_client = unwrap_result!(test_utils::get_client());
+        let client = Arc::new(Mutex::new(test_client));
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut directory, _) = unwrap_result!(dir_helper.create("DirName".to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                None));
+        let (mut child_directory, _) =
+            unwrap_result!(dir_helper.create("Child".to_string(),
+                                             ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                             Vec::new(),
+                                             true,
+                                             ::AccessLevel::Private,
+                                             Some(&mut directory)));
+        let new_file = |name: &str, size: u64, mime_type: Option<&str>| {
+            let mut metadata = FileMetadata::new(name.to_string(), Vec::new());
+            metadata.set_size(size);
+            if let Some(mime_type) = mime_type {
+                metadata.set_mime_type(mime_type.to_string());
+            }
+            unwrap_result!(File::new(metadata, DataMap::None))
+        };
+        directory.upsert_file(new_file("a.txt", 10, None));
+        directory.upsert_file(new_file("README", 5, None));
+        child_directory.upsert_file(new_file("b.TXT", 20, None));
+        child_directory.upsert_file(new_file("photo", 100, Some("image/png")));
+        let _ = unwrap_result!(dir_helper.update(&child_directory));
+        directory.upsert_sub_directory(child_directory.get_metadata().clone());
+        let _ = unwrap_result!(dir_helper.update(&directory));
+
+        let breakdown = unwrap_result!(dir_helper.get_size_breakdown(&directory));
+        assert_eq!(breakdown.len(), 3);
+        assert_eq!(breakdown.get("txt"), Some(&30));
+        assert_eq!(breakdown.get("image/png"), Some(&100));
+        assert_eq!(breakdown.get("_unknown"), Some(&5));
+    }
+
+    #[test]
+    fn get_batch() {
+        let test_client = unwrap_result!(test_utils::get_client());
+        let client = Arc::new(Mutex::new(test_client));
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let mut keys = Vec::new();
+        for name in &["First", "Second", "Third"] {
+            let (directory, _) = unwrap_result!(dir_helper.create(name.to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                None));
+            keys.push(directory.get_key().clone());
+        }
+
+        let directories = unwrap_result!(dir_helper.get_batch_all_ok(&keys));
+        let names: Vec<&str> = directories.iter()
+                                          .map(|directory| &directory.get_metadata().get_name()[..])
+                                          .collect();
+        assert_eq!(names, vec!["First", "Second", "Third"]);
+
+        let missing_id = XorName::new(unwrap_result!(utility::generate_random_array_u8_64()));
+        let missing_key = DirectoryKey::new(missing_id,
+                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                            true,
+                                            ::AccessLevel::Private);
+        keys.insert(1, missing_key);
+        let results = dir_helper.get_batch(&keys);
+        assert_eq!(results.len(), 4);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+        assert!(results[3].is_ok());
+        assert!(dir_helper.get_batch_all_ok(&keys).is_err());
+
+        // More keys than threads
+        let repeated_keys: Vec<DirectoryKey> = keys.iter()
+                                                   .cycle()
+                                                   .take(2 * BATCH_FETCH_THREADS + 1)
+                                                   .cloned()
+                                                   .collect();
+        let results = dir_helper.get_batch(&repeated_keys);
+        assert_eq!(results.len(), repeated_keys.len());
+        for (index, result) in results.iter().enumerate() {
+            assert_eq!(result.is_ok(), index % keys.len() != 1);
+        }
+    }
+
+    #[test]
+    fn set_directory_description() {
+        let test_client = unwrap_result!(test_utils::get_client());
+        let client = Arc::new(Mutex::new(test_client));
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut parent, _) = unwrap_result!(dir_helper.create("Parent".to_string(),
+                                                             ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                             Vec::new(),
+                                                             true,
+                                                             ::AccessLevel::Private,
+                                                             None));
+        let (mut directory, _) = unwrap_result!(dir_helper.create("DirName".to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                Some(&mut parent)));
+        assert!(directory.get_description().is_none());
+
+        let too_long: String = ::std::iter::repeat('a').take(1025).collect();
+        assert!(dir_helper.set_description(&mut directory, too_long).is_err());
+        unwrap_result!(dir_helper.set_description(&mut directory, "Holiday photos".to_string()));
+
+        let directory = unwrap_result!(dir_helper.get(directory.get_key()));
+        assert_eq!(directory.get_description(), Some("Holiday photos"));
+        let parent = unwrap_result!(dir_helper.get(parent.get_key()));
+        let metadata = unwrap_option!(parent.find_sub_directory(&"DirName".to_string()),
+                                      "Directory not found");
+        assert_eq!(metadata.get_description(), Some("Holiday photos"));
+    }
+
+    #[test]
+    fn find_files_in_tree() {
+        let test_client = unwrap_result!(test_utils::get_client());
+        let client = Arc::new(Mutex::new(test_client));
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut root, _) = unwrap_result!(dir_helper.create("Root".to_string(),
+                                                           ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                           Vec::new(),
+                                                           true,
+                                                           ::AccessLevel::Private,
+                                                           None));
+        let (mut photos, _) = unwrap_result!(dir_helper.create("photos".to_string(),
+                                                             ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                             Vec::new(),
+                                                             true,
+                                                             ::AccessLevel::Private,
+                                                             Some(&mut root)));
+        let (mut holiday, _) = unwrap_result!(dir_helper.create("holiday".to_string(),
+                                                              ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                              Vec::new(),
+                                                              true,
+                                                              ::AccessLevel::Private,
+                                                              Some(&mut photos)));
+        let (mut docs, _) = unwrap_result!(dir_helper.create("docs".to_string(),
+                                                           ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                           Vec::new(),
+                                                           true,
+                                                           ::AccessLevel::Private,
+                                                           Some(&mut root)));
+        let new_file = |name: &str| {
+            unwrap_result!(File::new(FileMetadata::new(name.to_string(), Vec::new()),
+                                     DataMap::None))
+        };
+        let deep_file = new_file("notes.txt");
+        holiday.upsert_file(deep_file.clone());
+        let _ = unwrap_result!(dir_helper.update(&holiday));
+        let shallow_file = new_file("notes.txt");
+        docs.upsert_file(shallow_file.clone());
+        let _ = unwrap_result!(dir_helper.update(&docs));
+        let root = unwrap_result!(dir_helper.get(root.get_key()));
+
+        let found = unwrap_result!(dir_helper.find_file_anywhere(&root, "notes.txt"));
+        let (path, file) = unwrap_option!(found, "File not found");
+        assert_eq!(path, vec!["docs".to_string()]);
+        assert_eq!(file, shallow_file);
+
+        let all = unwrap_result!(dir_helper.find_all_files_named(&root, "notes.txt"));
+        assert_eq!(all,
+                   vec![(vec!["docs".to_string()], shallow_file),
+                        (vec!["photos".to_string(), "holiday".to_string()], deep_file)]);
+
+        assert!(unwrap_result!(dir_helper.find_file_anywhere(&root, "missing.txt")).is_none());
+    }
+
+    #[test]
+    fn deep_clone_directory_tree() {
+        let test_client = unwrap_result!(test_utils::get_client());
+        let client = Arc::new(Mutex::new(test_client));
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let create = |name: &str, parent: Option<&mut DirectoryListing>| {
+            unwrap_result!(dir_helper.create(name.to_string(),
+                                             ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                             Vec::new(),
+                                             true,
+                                             ::AccessLevel::Private,
+                                             parent))
+                .0
+        };
+        let new_file = |name: &str| {
+            unwrap_result!(File::new(FileMetadata::new(name.to_string(), Vec::new()),
+                                     DataMap::None))
+        };
+        let mut root = create("Root", None);
+        let mut source = create("Source", Some(&mut root));
+        let mut child = create("Child", Some(&mut source));
+        source.upsert_file(new_file("a.txt"));
+        let _ = unwrap_result!(dir_helper.update(&source));
+        child.upsert_file(new_file("b.txt"));
+        let _ = unwrap_result!(dir_helper.update(&child));
+        let source = unwrap_result!(dir_helper.get(source.get_key()));
+
+        let copy = unwrap_result!(dir_helper.deep_clone(&source, &mut root, "Copy".to_string()));
+        assert!(root.find_sub_directory(&"Copy".to_string()).is_some());
+        assert!(copy.get_key() != source.get_key());
+        let copied_file = unwrap_option!(copy.find_file(&"a.txt".to_string()), "File not found");
+        let source_file = unwrap_option!(source.find_file(&"a.txt".to_string()),
+                                         "File not found");
+        assert!(copied_file.get_id() != source_file.get_id());
+        assert_eq!(copied_file.get_datamap(), source_file.get_datamap());
+
+        let copy = unwrap_result!(dir_helper.get(copy.get_key()));
+        let copied_child_metadata = unwrap_option!(copy.find_sub_directory(&"Child".to_string()),
+                                                   "Directory not found");
+        assert!(copied_child_metadata.get_key() != child.get_key());
+        let copied_child = unwrap_result!(dir_helper.get(copied_child_metadata.get_key()));
+        assert!(copied_child.find_file(&"b.txt".to_string()).is_some());
+
+        // Changes to the copy do not affect the source
+        let mut copied_child = copied_child;
+        let _ = unwrap_result!(copied_child.remove_file(&"b.txt".to_string()));
+        let _ = unwrap_result!(dir_helper.update(&copied_child));
+        let child = unwrap_result!(dir_helper.get(child.get_key()));
+        assert!(child.find_file(&"b.txt".to_string()).is_some());
+        assert_eq!(unwrap_result!(dir_helper.get(root.get_key())).get_sub_directories().len(), 2);
+    }
+
+    #[test]
+    fn copy_directory_tree() {
+        let test_client = unwrap_result!(test_utils::get_client());
+        let client = Arc::new(Mutex::new(test_client));
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut source, _) = unwrap_result!(dir_helper.create("Source".to_string(),
+                                                             ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                             Vec::new(),
+                                                             true,
+                                                             ::AccessLevel::Private,
+                                                             None));
+        let (mut child, _) = unwrap_result!(dir_helper.create("Child".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            Some(&mut source)));
+        child.upsert_file(unwrap_result!(File::new(FileMetadata::new("b.txt".to_string(),
+                                                                     Vec::new()),
+                                                   DataMap::None)));
+        let _ = unwrap_result!(dir_helper.update(&child));
+        let source = unwrap_result!(dir_helper.get(source.get_key()));
+
+        let copy = unwrap_result!(dir_helper.copy_directory(&source, "Copy".to_string(), None));
+        assert!(copy.get_key() != source.get_key());
+        assert_eq!(copy.get_key().is_versioned(), source.get_key().is_versioned());
+        assert_eq!(copy.get_key().get_access_level(), source.get_key().get_access_level());
+        assert_eq!(*copy.get_metadata().get_name(), "Copy".to_string());
+
+        // Changes to the source do not affect the copy
+        let mut child = unwrap_result!(dir_helper.get(child.get_key()));
+        let _ = unwrap_result!(child.remove_file(&"b.txt".to_string()));
+        let _ = unwrap_result!(dir_helper.update(&child));
+        let copy = unwrap_result!(dir_helper.get(copy.get_key()));
+        let copied_child_metadata = unwrap_option!(copy.find_sub_directory(&"Child".to_string()),
+                                                   "Directory not found");
+        let copied_child = unwrap_result!(dir_helper.get(copied_child_metadata.get_key()));
+        assert!(copied_child.find_file(&"b.txt".to_string()).is_some());
+
+        // Copying into a parent saves the parent with the copy added
+        let _ = unwrap_result!(dir_helper.copy_directory(&source,
+                                                         "Copy".to_string(),
+                                                         Some(&copy)));
+        let copy = unwrap_result!(dir_helper.get(copy.get_key()));
+        assert!(copy.find_sub_directory(&"Copy".to_string()).is_some());
+    }
+
+    #[test]
+    fn walk_directory_tree() {
+        use std::collections::HashSet;
+
+        let test_client = unwrap_result!(test_utils::get_client());
+        let client = Arc::new(Mutex::new(test_client));
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let create = |name: &str, parent: Option<&mut DirectoryListing>| {
+            unwrap_result!(dir_helper.create(name.to_string(),
+                                             ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                             Vec::new(),
+                                             true,
+                                             ::AccessLevel::Private,
+                                             parent))
+                .0
+        };
+        let add_file = |directory: &mut DirectoryListing, name: &str| {
+            directory.upsert_file(unwrap_result!(File::new(FileMetadata::new(name.to_string(),
+                                                                             Vec::new()),
+                                                           DataMap::None)));
+            let _ = unwrap_result!(dir_helper.update(directory));
+        };
+        // Root (a.txt) -> Child (b.txt) -> Grandchild (c.txt)
+        //              -> Sibling (d.txt)
+        let mut root = create("Root", None);
+        let mut child = create("Child", Some(&mut root));
+        let mut grandchild = create("Grandchild", Some(&mut child));
+        let mut sibling = create("Sibling", Some(&mut root));
+        add_file(&mut root, "a.txt");
+        add_file(&mut child, "b.txt");
+        add_file(&mut grandchild, "c.txt");
+        add_file(&mut sibling, "d.txt");
+        let root = unwrap_result!(dir_helper.get(root.get_key()));
+        let names = |names: &[&str]| {
+            names.iter().map(|name| name.to_string()).collect::<HashSet<String>>()
+        };
+
+        let mut visited = HashSet::new();
+        unwrap_result!(dir_helper.walk(&root, |_, file| {
+            let _ = visited.insert(file.get_name().clone());
+            WalkControl::Continue
+        }));
+        assert_eq!(visited, names(&["a.txt", "b.txt", "c.txt", "d.txt"]));
+
+        let mut visited = HashSet::new();
+        unwrap_result!(dir_helper.walk(&root, |directory, file| {
+            let _ = visited.insert(file.get_name().clone());
+            if *directory.get_metadata().get_name() == "Child".to_string() {
+                WalkControl::SkipDirectory
+            } else {
+                WalkControl::Continue
+            }
+        }));
+        assert_eq!(visited, names(&["a.txt", "b.txt", "d.txt"]));
+
+        let mut visited = HashSet::new();
+        unwrap_result!(dir_helper.walk(&root, |_, file| {
+            let _ = visited.insert(file.get_name().clone());
+            WalkControl::Stop
+        }));
+        assert_eq!(visited, names(&["a.txt"]));
+
+        // Sub-directories are offered to the directory visitor before they are fetched
+        let mut visited = HashSet::new();
+        let mut offered = HashSet::new();
+        unwrap_result!(dir_helper.walk_with_directories(&root,
+                                                        |_, file| {
+                                                            let _ = visited.insert(file.get_name()
+                                                                                       .clone());
+                                                            WalkControl::Continue
+                                                        },
+                                                        |_, metadata| {
+                                                            let name = metadata.get_name().clone();
+                                                            let _ = offered.insert(name);
+                                                            if *metadata.get_name() == "Child" {
+                                                                WalkControl::SkipDirectory
+                                                            } else {
+                                                                WalkControl::Continue
+                                                            }
+                                                        }));
+        assert_eq!(visited, names(&["a.txt", "d.txt"]));
+        assert_eq!(offered, names(&["Child", "Sibling"]));
+    }
+
+    #[test]
+    fn update_and_return_directory() {
+        let test_client = unwrap_result!(test_utils::get_client());
+        let client = Arc::new(Mutex::new(test_client));
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut directory, _) = unwrap_result!(dir_helper.create("DirName".to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                None));
+        directory.get_mut_metadata().set_user_metadata(vec![1u8; 10]);
+        let expected = directory.clone();
+        {
+            let updated_directory = unwrap_result!(dir_helper.update_and_return(&mut directory));
+            assert_eq!(*updated_directory, expected);
+        }
+        assert_eq!(unwrap_result!(dir_helper.get(directory.get_key())), directory);
+    }
+
     #[test]
     fn delete_directory() {
         let test_client = unwrap_result!(test_utils::get_client());
@@ -627,4 +2073,238 @@ mod test {
                                                                             .get_name()));
         assert!(delete_result.is_none());
     }
+
+    #[test]
+    fn export_directory_tree() {
+        let test_client = unwrap_result!(test_utils::get_client());
+        let client = Arc::new(Mutex::new(test_client));
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let file_helper = ::helper::file_helper::FileHelper::new(client.clone());
+        let (mut root, _) = unwrap_result!(dir_helper.create("Root".to_string(),
+                                                           ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                           Vec::new(),
+                                                           true,
+                                                           ::AccessLevel::Private,
+                                                           None));
+        let (child, _) = unwrap_result!(dir_helper.create("Child".to_string(),
+                                                        ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                        Vec::new(),
+                                                        true,
+                                                        ::AccessLevel::Private,
+                                                        Some(&mut root)));
+        let mut writer = unwrap_result!(file_helper.create("b.txt".to_string(),
+                                                           Vec::new(),
+                                                           child));
+        writer.write(&"World!".to_string().into_bytes(), 0);
+        let _ = unwrap_result!(writer.close());
+
+        let mut root = unwrap_result!(dir_helper.get(root.get_key()));
+        root.upsert_file(unwrap_result!(File::new(FileMetadata::new("..".to_string(), Vec::new()),
+                                                  DataMap::None)));
+        let mut writer = unwrap_result!(file_helper.create("a.txt".to_string(),
+                                                           Vec::new(),
+                                                           root));
+        writer.write(&"Hello".to_string().into_bytes(), 0);
+        let (root, _) = unwrap_result!(writer.close());
+
+        let dest = env::temp_dir().join(generate_random_file_name());
+        let stats = unwrap_result!(dir_helper.export_tree(&root, &dest));
+        assert_eq!(stats.total_files, 2);
+        assert_eq!(stats.total_bytes, 11);
+        assert_eq!(stats.skipped, vec!["..".to_string()]);
+
+        let read_local = |path: PathBuf| {
+            let mut content = String::new();
+            let _ = unwrap_result!(unwrap_result!(fs::File::open(path))
+                                       .read_to_string(&mut content));
+            content
+        };
+        assert_eq!(read_local(dest.join("a.txt")), "Hello".to_string());
+        assert_eq!(read_local(dest.join("Child").join("b.txt")), "World!".to_string());
+        unwrap_result!(fs::remove_dir_all(&dest));
+    }
+
+    #[test]
+    fn import_and_export_directory_tree() {
+        let test_client = unwrap_result!(test_utils::get_client());
+        let client = Arc::new(Mutex::new(test_client));
+        let dir_helper = DirectoryHelper::new(client.clone());
+
+        let temp_dir = env::temp_dir().join(generate_random_file_name());
+        let source = temp_dir.join("Source");
+        let files: Vec<(PathBuf, Vec<u8>)> =
+            vec![(PathBuf::from("a.txt"), "Hello".to_string().into_bytes()),
+                 (PathBuf::from("Child").join("b.json"), "{}".to_string().into_bytes()),
+                 (PathBuf::from("Child").join("Grandchild").join("c.bin"), vec![0u8, 1, 2])];
+        unwrap_result!(fs::create_dir_all(source.join("Child").join("Grandchild")));
+        unwrap_result!(fs::create_dir_all(source.join("Empty")));
+        for &(ref path, ref content) in &files {
+            unwrap_result!(unwrap_result!(fs::File::create(source.join(path))).write_all(content));
+        }
+
+        let (directory, stats) = unwrap_result!(dir_helper.import_tree(&source, None));
+        assert_eq!(*directory.get_metadata().get_name(), "Source".to_string());
+        assert_eq!(stats.files_uploaded, 3);
+        assert_eq!(stats.bytes_transferred, 10);
+        assert!(stats.failed.is_empty());
+        assert_eq!(directory.get_sub_directories().len(), 2);
+        let file = unwrap_option!(directory.find_file(&"a.txt".to_string()), "File not found");
+        assert_eq!(file.get_metadata().get_mime_type(), Some("text/plain"));
+
+        let dest = temp_dir.join("Exported");
+        let export_stats = unwrap_result!(dir_helper.export_tree(&directory, &dest));
+        assert_eq!(export_stats.total_files, 3);
+        assert!(dest.join("Empty").is_dir());
+        for &(ref path, ref content) in &files {
+            let mut exported = Vec::new();
+            let _ = unwrap_result!(unwrap_result!(fs::File::open(dest.join(path)))
+                                       .read_to_end(&mut exported));
+            assert_eq!(exported, *content);
+        }
+        unwrap_result!(fs::remove_dir_all(&temp_dir));
+    }
+
+    #[test]
+    fn list_all_files_in_tree() {
+        let test_client = unwrap_result!(test_utils::get_client());
+        let client = Arc::new(Mutex::new(test_client));
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let create = |name: &str, parent: Option<&mut DirectoryListing>| {
+            unwrap_result!(dir_helper.create(name.to_string(),
+                                             ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                             Vec::new(),
+                                             true,
+                                             ::AccessLevel::Private,
+                                             parent))
+                .0
+        };
+        let add_file = |directory: &mut DirectoryListing, name: &str| {
+            directory.upsert_file(unwrap_result!(File::new(FileMetadata::new(name.to_string(),
+                                                                             Vec::new()),
+                                                           DataMap::None)));
+            let _ = unwrap_result!(dir_helper.update(directory));
+        };
+        // Root (a.txt) -> Middle -> Leaf (b.txt, c.txt)
+        //              -> Empty
+        let mut root = create("Root", None);
+        assert!(unwrap_result!(dir_helper.list_all_files(&root)).is_empty());
+        let mut middle = create("Middle", Some(&mut root));
+        let _ = create("Empty", Some(&mut root));
+        let mut leaf = create("Leaf", Some(&mut middle));
+        add_file(&mut leaf, "b.txt");
+        add_file(&mut leaf, "c.txt");
+        let mut root = unwrap_result!(dir_helper.get(root.get_key()));
+        add_file(&mut root, "a.txt");
+
+        let listed: Vec<(Vec<String>, String)> =
+            unwrap_result!(dir_helper.list_all_files(&root))
+                .into_iter()
+                .map(|(path, file)| (path, file.get_name().clone()))
+                .collect();
+        let leaf_path = vec!["Middle".to_string(), "Leaf".to_string()];
+        assert_eq!(listed,
+                   vec![(Vec::new(), "a.txt".to_string()),
+                        (leaf_path.clone(), "b.txt".to_string()),
+                        (leaf_path, "c.txt".to_string())]);
+    }
+
+    #[test]
+    fn get_latest_directory() {
+        let test_client = unwrap_result!(test_utils::get_client());
+        let client = Arc::new(Mutex::new(test_client));
+        let dir_helper = DirectoryHelper::new(client.clone());
+        for &(tag_type, versioned) in &[(::VERSIONED_DIRECTORY_LISTING_TAG, true),
+                                        (::UNVERSIONED_DIRECTORY_LISTING_TAG, false)] {
+            let (mut directory, _) = unwrap_result!(dir_helper.create("DirName".to_string(),
+                                                                    tag_type,
+                                                                    Vec::new(),
+                                                                    versioned,
+                                                                    ::AccessLevel::Private,
+                                                                    None));
+            assert_eq!(unwrap_result!(dir_helper.get_latest(directory.get_key())), directory);
+
+            let metadata = FileMetadata::new("a.txt".to_string(), Vec::new());
+            directory.upsert_file(unwrap_result!(File::new(metadata, DataMap::None)));
+            let _ = unwrap_result!(dir_helper.update(&directory));
+            let latest = unwrap_result!(dir_helper.get_latest(directory.get_key()));
+            assert_eq!(latest, directory);
+            assert!(latest.find_file(&"a.txt".to_string()).is_some());
+        }
+
+        // The cache is bypassed
+        let cached_dir_helper = DirectoryHelper::new_with_cache(client.clone(), 1);
+        let (directory, _) =
+            unwrap_result!(cached_dir_helper.create("Cached".to_string(),
+                                                    ::UNVERSIONED_DIRECTORY_LISTING_TAG,
+                                                    Vec::new(),
+                                                    false,
+                                                    ::AccessLevel::Private,
+                                                    None));
+        let mut cached_directory = directory.clone();
+        let metadata = FileMetadata::new("cached.txt".to_string(), Vec::new());
+        cached_directory.upsert_file(unwrap_result!(File::new(metadata, DataMap::None)));
+        cached_dir_helper.insert_cached((directory.get_key().get_id().clone(), None),
+                                        cached_directory.clone());
+        assert_eq!(unwrap_result!(cached_dir_helper.get(directory.get_key())),
+                   cached_directory);
+        assert_eq!(unwrap_result!(cached_dir_helper.get_latest(directory.get_key())),
+                   directory);
+    }
+
+    #[test]
+    fn cached_directory_listings() {
+        let test_client = unwrap_result!(test_utils::get_client());
+        let client = Arc::new(Mutex::new(test_client));
+        let dir_helper = DirectoryHelper::new_with_cache(client.clone(), 2);
+        let new_file = |name: &str| {
+            let metadata = FileMetadata::new(name.to_string(), Vec::new());
+            unwrap_result!(File::new(metadata, DataMap::None))
+        };
+
+        for &(tag_type, versioned) in &[(::VERSIONED_DIRECTORY_LISTING_TAG, true),
+                                        (::UNVERSIONED_DIRECTORY_LISTING_TAG, false)] {
+            let (mut directory, _) = unwrap_result!(dir_helper.create("DirName".to_string(),
+                                                                    tag_type,
+                                                                    Vec::new(),
+                                                                    versioned,
+                                                                    ::AccessLevel::Private,
+                                                                    None));
+            let cache_key = (directory.get_key().get_id().clone(), None);
+            assert_eq!(unwrap_result!(dir_helper.get(directory.get_key())), directory);
+
+            // A cached listing is returned without fetching it again
+            let mut cached_directory = directory.clone();
+            cached_directory.upsert_file(new_file("cached.txt"));
+            dir_helper.insert_cached(cache_key.clone(), cached_directory.clone());
+            assert_eq!(unwrap_result!(dir_helper.get(directory.get_key())),
+                       cached_directory);
+
+            directory.upsert_file(new_file("a.txt"));
+            let _ = unwrap_result!(dir_helper.update(&directory));
+            assert!(dir_helper.get_cached(&cache_key).is_none());
+            assert_eq!(unwrap_result!(dir_helper.get(directory.get_key())), directory);
+        }
+
+        // A stale cached parent is not used to update the parent
+        let mut root_dir = unwrap_result!(dir_helper.get_user_root_directory_listing());
+        let (mut directory, _) = unwrap_result!(dir_helper.create("Child".to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                Some(&mut root_dir)));
+        let root_key = root_dir.get_key().clone();
+        let mut stale_root_dir = unwrap_result!(dir_helper.get(&root_key));
+        stale_root_dir.upsert_file(new_file("stale.txt"));
+        dir_helper.insert_cached((root_key.get_id().clone(), None), stale_root_dir);
+        directory.upsert_file(new_file("a.txt"));
+        let updated_root_dir = unwrap_option!(unwrap_result!(dir_helper.update(&directory)),
+                                              "Parent not updated");
+        assert!(updated_root_dir.find_file(&"stale.txt".to_string()).is_none());
+        assert!(unwrap_result!(dir_helper.get(&root_key))
+                    .find_file(&"stale.txt".to_string())
+                    .is_none());
+
+        assert!(DirectoryHelper::new_with_cache(client, 0).cache.is_none());
+    }
 }
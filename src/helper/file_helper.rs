@@ -15,27 +15,83 @@
 // Please review the Licences for the specific language governing permissions and limitations
 // relating to use of the SAFE Network Software.
 
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
 use std::sync::{Arc, Mutex};
+use std::thread;
 
 use directory_listing::DirectoryListing;
 use errors::NfsError;
 use file::File;
-use helper::directory_helper::DirectoryHelper;
+use helper::directory_helper::{DirectoryHelper, WalkControl};
 use helper::reader::Reader;
+use helper::write_lock::WriteLock;
 use helper::writer::{Mode, Writer};
-use metadata::file_metadata::FileMetadata;
+use maidsafe_utilities::serialisation::{serialise, deserialise};
+use metadata::file_metadata::{CURRENT_SCHEMA_VERSION, FileMetadata, MAX_TAG_SIZE};
+use rustc_serialize::{Decodable, Encodable};
+use rustc_serialize::base64::FromBase64;
+use rustc_serialize::hex::ToHex;
 use safe_core::client::Client;
-use self_encryption::DataMap;
+use safe_core::errors::CoreError;
+use safe_core::utility;
+use safe_core::SelfEncryptionStorage;
+use self_encryption::{DataMap, SelfEncryptor};
+use sodiumoxide::crypto::box_;
+use sodiumoxide::crypto::secretbox;
+use utils;
+use xor_name::XorName;
+
+/// Size of the plain text chunks into which content is split before symmetric encryption
+pub const ENCRYPTED_CHUNK_SIZE: usize = 1024 * 1024;
+// Size of the Poly1305 authenticator added to each encrypted chunk
+const ENCRYPTED_CHUNK_MAC_SIZE: usize = 16;
+
+/// Statistics about the change history of a File, computed from its versions
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileSummary {
+    /// Number of versions of the File
+    pub version_count: usize,
+    /// Time of creation of the first version
+    pub first_created: ::time::Tm,
+    /// Time of modification of the latest version
+    pub last_modified: ::time::Tm,
+    /// Smallest size among all the versions
+    pub min_size: u64,
+    /// Largest size among all the versions
+    pub max_size: u64,
+    /// Size of the latest version
+    pub current_size: u64,
+}
+
+/// A version of a File along with the version of its parent directory which introduced it
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileVersion {
+    /// The File as it was in this version
+    pub file: File,
+    /// Modified time recorded in the metadata of the parent directory version which introduced
+    /// the File. It is set by the client which updated the directory, from its local clock, so it
+    /// is not a network timestamp and is not guaranteed to increase with `version_index`
+    pub directory_modified_time: ::time::Tm,
+    /// Index of the parent directory version, counted from the oldest version
+    pub version_index: usize,
+}
 
 /// File provides helper functions to perform Operations on Files
 pub struct FileHelper {
     client: Arc<Mutex<Client>>,
+    directory_helper: DirectoryHelper,
 }
 
 impl FileHelper {
     /// Create a new FileHelper instance
     pub fn new(client: Arc<Mutex<Client>>) -> FileHelper {
-        FileHelper { client: client }
+        FileHelper {
+            directory_helper: DirectoryHelper::new(client.clone()),
+            client: client,
+        }
     }
 
     /// Helper function to create a file in a directory listing
@@ -48,15 +104,166 @@ impl FileHelper {
                   user_metatdata: Vec<u8>,
                   parent_directory: DirectoryListing)
                   -> Result<Writer, NfsError> {
-        match parent_directory.find_file(&name) {
+        self.create_with_tags(name, user_metatdata, Vec::new(), parent_directory)
+    }
+
+    /// Helper function to create a file with the specified MIME type in a directory listing.
+    /// Refer `create` for details about the returned writer
+    pub fn create_with_mime_type(&self,
+                                 name: String,
+                                 user_metatdata: Vec<u8>,
+                                 mime_type: Option<String>,
+                                 parent_directory: DirectoryListing)
+                                 -> Result<Writer, NfsError> {
+        let mut metadata = FileMetadata::new(name, user_metatdata);
+        if let Some(mime_type) = mime_type {
+            metadata.set_mime_type(mime_type);
+        }
+        self.create_with_metadata(metadata, parent_directory)
+    }
+
+    /// Helper function to create a file with the specified tags in a directory listing.
+    /// Each tag must be non-empty and at most `MAX_TAG_SIZE` bytes long.
+    /// Refer `create` for details about the returned writer
+    pub fn create_with_tags(&self,
+                            name: String,
+                            user_metatdata: Vec<u8>,
+                            tags: Vec<String>,
+                            parent_directory: DirectoryListing)
+                            -> Result<Writer, NfsError> {
+        if tags.iter().any(|tag| tag.is_empty() || tag.len() > MAX_TAG_SIZE) {
+            return Err(NfsError::ParameterIsNotValid);
+        }
+        let mut metadata = FileMetadata::new(name, user_metatdata);
+        metadata.set_tags(tags);
+        self.create_with_metadata(metadata, parent_directory)
+    }
+
+    /// Returns a writer for a new file with the given metadata, as explained for `create`
+    fn create_with_metadata(&self,
+                            metadata: FileMetadata,
+                            parent_directory: DirectoryListing)
+                            -> Result<Writer, NfsError> {
+        match parent_directory.find_file(metadata.get_name()) {
             Some(_) => Err(NfsError::FileAlreadyExistsWithSameName),
             None => {
-                let file = try!(File::new(FileMetadata::new(name, user_metatdata), DataMap::None));
+                let file = try!(File::new(metadata, DataMap::None));
                 Ok(Writer::new(self.client.clone(), Mode::Overwrite, parent_directory, file))
             }
         }
     }
 
+    /// Creates a file for each (name, user_metadata, content) in `files` and saves the
+    /// DirectoryListing with a single update, instead of one update per file as with `create`.
+    /// Fails without creating any file if a name is repeated or already exists in the directory.
+    /// Returns (parent_directory, Option<parent_directory's parent>)
+    pub fn bulk_create(&self,
+                       files: Vec<(String, Vec<u8>, Vec<u8>)>,
+                       parent_directory: DirectoryListing)
+                       -> Result<(DirectoryListing, Option<DirectoryListing>), NfsError> {
+        for (index, &(ref name, _, _)) in files.iter().enumerate() {
+            if parent_directory.find_file(name).is_some() ||
+               files[..index].iter().any(|&(ref other_name, _, _)| other_name == name) {
+                return Err(NfsError::FileAlreadyExistsWithSameName);
+            }
+        }
+
+        debug!("Creating {:?} files ...", files.len());
+        let mut parent_directory = parent_directory;
+        for (name, user_metadata, content) in files {
+            let mut self_encryptor =
+                SelfEncryptor::new(SelfEncryptionStorage::new(self.client.clone()),
+                                   DataMap::None);
+            self_encryptor.write(&content, 0);
+            let mut metadata = FileMetadata::new(name, user_metadata);
+            metadata.set_size(self_encryptor.len());
+            metadata.set_checksum(Some(utils::sha3_256(&content)));
+            parent_directory.upsert_file(try!(File::new(metadata, self_encryptor.close())));
+        }
+        let updated_parent = try!(self.directory_helper.update(&parent_directory));
+        Ok((parent_directory, updated_parent))
+    }
+
+    /// Searches the directory tree rooted at `root` for files whose name matches the glob
+    /// `pattern`, in which `*` matches any sequence of characters and `?` any single character.
+    /// Returns each match along with the DirectoryListing containing it
+    pub fn find_in_tree(&self,
+                        root: &DirectoryListing,
+                        pattern: &str)
+                        -> Result<Vec<(DirectoryListing, File)>, NfsError> {
+        let mut matches = Vec::new();
+        try!(self.directory_helper.walk(root, |directory, file| {
+            if utils::matches_glob(file.get_name(), pattern) {
+                matches.push((directory.clone(), file.clone()));
+            }
+            WalkControl::Continue
+        }));
+        Ok(matches)
+    }
+
+    /// Creates a file with `content` encrypted using the symmetric `key`. The content is split into
+    /// chunks of ENCRYPTED_CHUNK_SIZE bytes, each stored as (nonce || cipher text).
+    /// Chunks are sealed with sodiumoxide's secretbox (XSalsa20-Poly1305) and a random nonce each.
+    /// It is authenticated encryption like AES-GCM, which sodiumoxide does not provide, and is
+    /// what safe_core already uses for symmetric encryption.
+    /// Returns (parent_directory, Option<parent_directory's parent>)
+    pub fn create_encrypted(&self,
+                            name: String,
+                            user_metatdata: Vec<u8>,
+                            content: &[u8],
+                            key: &[u8; 32],
+                            parent_directory: DirectoryListing)
+                            -> Result<(DirectoryListing, Option<DirectoryListing>), NfsError> {
+        if parent_directory.find_file(&name).is_some() {
+            return Err(NfsError::FileAlreadyExistsWithSameName);
+        }
+        let mut metadata = FileMetadata::new(name, user_metatdata);
+        metadata.set_content_encrypted(true);
+        let file = try!(File::new(metadata, DataMap::None));
+
+        debug!("Encrypting file content ...");
+        let key = secretbox::Key(*key);
+        let mut encrypted_content = Vec::new();
+        for chunk in content.chunks(ENCRYPTED_CHUNK_SIZE) {
+            let nonce = secretbox::gen_nonce();
+            encrypted_content.extend_from_slice(&nonce.0);
+            encrypted_content.extend(secretbox::seal(chunk, &nonce, &key));
+        }
+        let mut writer = Writer::new(self.client.clone(), Mode::Overwrite, parent_directory, file);
+        writer.write(&encrypted_content, 0);
+        writer.close()
+    }
+
+    /// Reads the content of a file created using `create_encrypted` and decrypts it with the
+    /// symmetric `key`. Returns `NfsError::DecryptionFailed` if `key` is not the key the file was
+    /// encrypted with or the content was tampered with
+    pub fn read_and_decrypt(&self, file: &File, key: &[u8; 32]) -> Result<Vec<u8>, NfsError> {
+        if !file.get_metadata().is_content_encrypted() {
+            return Err(NfsError::ParameterIsNotValid);
+        }
+        let mut reader = self.read(file);
+        let size = reader.size();
+        let encrypted_content = try!(reader.read(0, size));
+
+        debug!("Decrypting file content ...");
+        let key = secretbox::Key(*key);
+        let encrypted_chunk_size = secretbox::NONCEBYTES + ENCRYPTED_CHUNK_SIZE +
+                                   ENCRYPTED_CHUNK_MAC_SIZE;
+        let mut content = Vec::with_capacity(encrypted_content.len());
+        for encrypted_chunk in encrypted_content.chunks(encrypted_chunk_size) {
+            if encrypted_chunk.len() < secretbox::NONCEBYTES {
+                return Err(NfsError::DecryptionFailed);
+            }
+            let (nonce, cipher_text) = encrypted_chunk.split_at(secretbox::NONCEBYTES);
+            let nonce = try!(secretbox::Nonce::from_slice(nonce)
+                                 .ok_or(NfsError::DecryptionFailed));
+            let chunk = try!(secretbox::open(cipher_text, &nonce, &key)
+                                 .map_err(|()| NfsError::DecryptionFailed));
+            content.extend(chunk);
+        }
+        Ok(content)
+    }
+
     /// Delete a file from the DirectoryListing
     /// Returns Option<parent_directory's parent>
     pub fn delete(&self,
@@ -64,28 +271,456 @@ impl FileHelper {
                   parent_directory: &mut DirectoryListing)
                   -> Result<Option<DirectoryListing>, NfsError> {
         debug!("Deleting {:?} file from directory listing ...", file_name);
-        try!(parent_directory.remove_file(&file_name));
-        let directory_helper = DirectoryHelper::new(self.client.clone());
-        directory_helper.update(&parent_directory)
+        try!(parent_directory.remove_file(&file_name)
+                             .map_err(|error| error.with_context(&file_name[..])));
+        self.directory_helper.update(&parent_directory)
+    }
+
+    /// Deletes the files named in `file_names` from the DirectoryListing, saving it with a single
+    /// update. If some of the files are not found, the remaining files are still deleted and
+    /// `NfsError::PartialFailure` is returned holding the number of files deleted and the name of
+    /// each missing file.
+    /// Returns (number of files deleted, Option<parent_directory's parent>)
+    pub fn bulk_delete(&self,
+                       file_names: &[&str],
+                       parent_directory: &mut DirectoryListing)
+                       -> Result<(usize, Option<DirectoryListing>), NfsError> {
+        let mut deleted = 0;
+        let mut errors = Vec::new();
+        for file_name in file_names {
+            let file_name = file_name.to_string();
+            match parent_directory.remove_file(&file_name) {
+                Ok(()) => deleted += 1,
+                Err(error) => errors.push((file_name, error)),
+            }
+        }
+
+        let updated_parent = if deleted > 0 {
+            debug!("Deleting {:?} files from directory listing ...", deleted);
+            try!(self.directory_helper.update(parent_directory))
+        } else {
+            None
+        };
+        if errors.is_empty() {
+            Ok((deleted, updated_parent))
+        } else {
+            Err(NfsError::PartialFailure {
+                succeeded: deleted,
+                errors: errors,
+            })
+        }
+    }
+
+    /// Copies `file` from `source_dir` into `dest_dir` as a new file with a fresh id. Only the
+    /// DataMap is cloned, so no content is read from or written to the network; just `dest_dir`
+    /// is updated.
+    /// Returns (dest_dir, Option<dest_dir's parent>)
+    pub fn copy_file(&self,
+                     file: &File,
+                     source_dir: &DirectoryListing,
+                     dest_dir: DirectoryListing)
+                     -> Result<(DirectoryListing, Option<DirectoryListing>), NfsError> {
+        {
+            let existing_file = try!(source_dir.find_file(file.get_name())
+                                               .ok_or(NfsError::FileNotFound));
+            if existing_file != file {
+                return Err(NfsError::FileDoesNotMatch);
+            }
+        }
+        if dest_dir.find_file(file.get_name()).is_some() {
+            return Err(NfsError::FileAlreadyExistsWithSameName);
+        }
+        debug!("Copying {:?} file ...", file.get_name());
+        let copy = try!(File::new(file.get_metadata().clone(), file.get_datamap().clone()));
+        let mut dest_dir = dest_dir;
+        dest_dir.upsert_file(copy);
+        let updated_parent = try!(self.directory_helper.update(&dest_dir));
+        Ok((dest_dir, updated_parent))
     }
 
-    /// Updates the file metadata.
+    /// Moves the file named `file_name` from `src` to `dest`, keeping its id. If `new_name` is
+    /// passed, the file is renamed to it. `src` and `dest` can be the same directory, which only
+    /// renames the file. `dest` is saved with the file added before `src` is saved with the file
+    /// removed, so a failure never leaves the file unreachable. `src` is only modified once it is
+    /// saved, and is updated to the saved DirectoryListing.
+    /// Returns (dest, Option<dest's parent>)
+    pub fn move_file(&self,
+                     file_name: &str,
+                     new_name: Option<String>,
+                     src: &mut DirectoryListing,
+                     dest: DirectoryListing)
+                     -> Result<(DirectoryListing, Option<DirectoryListing>), NfsError> {
+        let file_name = file_name.to_string();
+        let mut file = try!(src.find_file(&file_name)
+                               .cloned()
+                               .ok_or_else(|| NfsError::FileNotFound.with_context(&file_name[..])));
+        let new_name = new_name.unwrap_or_else(|| file_name.clone());
+        if dest.find_file(&new_name).is_some() {
+            return Err(NfsError::FileAlreadyExistsWithSameName);
+        }
+        let same_directory = src.get_key() == dest.get_key();
+        debug!("Moving {:?} file to {:?} ...", file_name, new_name);
+        let mut dest = dest;
+        if same_directory {
+            try!(dest.remove_file(&file_name));
+        }
+        file.get_mut_metadata().set_name(new_name);
+        dest.upsert_file(file);
+        let updated_parent = try!(self.directory_helper.update(&dest));
+        if same_directory {
+            *src = dest.clone();
+        } else {
+            let mut updated_src = src.clone();
+            try!(updated_src.remove_file(&file_name));
+            let _ = try!(self.directory_helper.update(&updated_src));
+            *src = updated_src;
+        }
+        Ok((dest, updated_parent))
+    }
+
+    /// Overwrites the content of the file with zeros and then deletes it from the directory, as a
+    /// best effort secure delete. Returns the updated parent directory.
+    /// Immutable data can not be deleted from the SAFE Network, so the original chunks of the
+    /// file remain in the Network. This only replaces the references to them held by the file
+    pub fn zero_out(&self,
+                    file: File,
+                    parent_directory: DirectoryListing)
+                    -> Result<DirectoryListing, NfsError> {
+        let file_name = file.get_name().clone();
+        let size = file.get_size();
+        debug!("Zeroing out {:?} bytes of file content ...", size);
+        let mut writer = try!(self.update_content(file, Mode::Overwrite, parent_directory));
+        writer.write_zeroes(size, 0);
+        let (mut parent_directory, _) = try!(writer.close());
+        let _ = try!(self.delete(file_name, &mut parent_directory));
+        Ok(parent_directory)
+    }
+
+    /// Updates the file metadata. The created time of the existing file is retained.
+    /// Returns `NfsError::FileLocked` if a write lock is held on the file.
     /// Returns Option<parent_directory's parent>
     pub fn update_metadata(&self,
-                           file: File,
+                           mut file: File,
                            parent_directory: &mut DirectoryListing)
                            -> Result<Option<DirectoryListing>, NfsError> {
-        {
+        let created_time = {
             let existing_file = try!(parent_directory.find_file_by_id(file.get_id())
                                                      .ok_or(NfsError::FileNotFound));
             if existing_file.get_name() != file.get_name() &&
                parent_directory.find_file(file.get_name()).is_some() {
                 return Err(NfsError::FileAlreadyExistsWithSameName);
             }
+            existing_file.get_metadata().get_created_time().clone()
+        };
+        let _ = try!(self.get_latest_unlocked(file.get_id(), parent_directory));
+        file.get_mut_metadata().set_created_time(created_time);
+        parent_directory.upsert_file(file);
+        self.directory_helper.update(&parent_directory)
+    }
+
+    /// Makes `version`, one of the files returned by `get_versions`, the current state of the file
+    /// again. The DataMap, size and user metadata of `version` are copied onto the current file,
+    /// which keeps its id, so no content needs to be written to the network. The version of the
+    /// file is incremented, so sessions holding the file before the restore have to fetch it again.
+    /// Returns `NfsError::FileLocked` if a write lock is held on the file.
+    /// Returns Option<parent_directory's parent>
+    pub fn restore_version(&self,
+                           version: &File,
+                           parent_directory: &mut DirectoryListing)
+                           -> Result<Option<DirectoryListing>, NfsError> {
+        let mut file = try!(parent_directory.find_file_by_id(version.get_id())
+                                            .cloned()
+                                            .ok_or(NfsError::FileNotFound));
+        let _ = try!(self.get_latest_unlocked(file.get_id(), parent_directory));
+        debug!("Restoring version of {:?} file ...", file.get_name());
+        file.set_datamap(version.get_datamap().clone());
+        file.increment_version();
+        {
+            let metadata = file.get_mut_metadata();
+            metadata.set_size(version.get_size());
+            metadata.set_checksum(version.get_metadata().get_checksum().cloned());
+            metadata.set_user_metadata(version.get_metadata().get_user_metadata().clone());
+            metadata.set_modified_time(::time::now_utc());
         }
         parent_directory.upsert_file(file);
-        let directory_helper = DirectoryHelper::new(self.client.clone());
-        directory_helper.update(&parent_directory)
+        self.directory_helper.update(&parent_directory)
+    }
+
+    /// Migrates the metadata of a file written under an older schema version to
+    /// CURRENT_SCHEMA_VERSION, applying the migration of each version in turn, and updates it.
+    /// If `dry_run` is true, the migrated file is only updated in `parent_directory` locally, so
+    /// the result can be previewed without any write to the network.
+    /// Metadata stored before schema versions were recorded has schema version 0.
+    /// Returns `NfsError::ParameterIsNotValid` for a schema version this version of the crate
+    /// has no migration for.
+    /// Returns Option<parent_directory's parent>
+    pub fn migrate_format(&self,
+                          mut file: File,
+                          parent_directory: &mut DirectoryListing,
+                          dry_run: bool)
+                          -> Result<Option<DirectoryListing>, NfsError> {
+        let schema_version = file.get_metadata().get_schema_version();
+        if schema_version > CURRENT_SCHEMA_VERSION {
+            return Err(NfsError::ParameterIsNotValid);
+        }
+        if schema_version == CURRENT_SCHEMA_VERSION {
+            return Ok(None);
+        }
+
+        debug!("Migrating file metadata from schema version {:?} ...", schema_version);
+        try!(FileHelper::migrate_metadata(file.get_mut_metadata()));
+        if dry_run {
+            parent_directory.upsert_file(file);
+            return Ok(None);
+        }
+        self.update_metadata(file, parent_directory)
+    }
+
+    fn migrate_metadata(metadata: &mut FileMetadata) -> Result<(), NfsError> {
+        while metadata.get_schema_version() < CURRENT_SCHEMA_VERSION {
+            match metadata.get_schema_version() {
+                0 => {
+                    // Tags were not validated before version 1
+                    let tags = metadata.get_tags()
+                                       .iter()
+                                       .filter(|tag| !tag.is_empty() && tag.len() <= MAX_TAG_SIZE)
+                                       .cloned()
+                                       .collect();
+                    metadata.set_tags(tags);
+                }
+                _ => return Err(NfsError::ParameterIsNotValid),
+            }
+            let next_version = metadata.get_schema_version() + 1;
+            metadata.set_schema_version(next_version);
+        }
+        Ok(())
+    }
+
+    /// Serialises `metadata` and stores it as the user metadata of the file.
+    /// Returns Option<parent_directory's parent>
+    pub fn update_user_metadata_cbor<T: Encodable>(&self,
+                                                   mut file: File,
+                                                   metadata: T,
+                                                   parent_directory: &mut DirectoryListing)
+                                                   -> Result<Option<DirectoryListing>, NfsError> {
+        file.get_mut_metadata().set_user_metadata(try!(serialise(&metadata)));
+        self.update_metadata(file, parent_directory)
+    }
+
+    /// Deserialises the user metadata of the file which was stored using
+    /// `update_user_metadata_cbor`
+    pub fn get_user_metadata_cbor<T: Decodable>(file: &File) -> Result<T, NfsError> {
+        Ok(try!(deserialise(file.get_metadata().get_user_metadata())))
+    }
+
+    /// Applies `update` to the metadata of every file in the directory for which `filter`
+    /// returns true and saves the directory with a single update. The files are updated on copies
+    /// so that nothing is modified if `update` panics.
+    /// Returns the number of files updated
+    pub fn bulk_update_metadata<F, U>(&self,
+                                      directory: &mut DirectoryListing,
+                                      filter: F,
+                                      update: U)
+                                      -> Result<usize, NfsError>
+        where F: Fn(&File) -> bool,
+              U: Fn(&mut FileMetadata)
+    {
+        let mut updated_files = Vec::new();
+        for file in directory.get_files().iter().filter(|file| filter(*file)) {
+            let mut updated_file = file.clone();
+            update(updated_file.get_mut_metadata());
+            updated_files.push(updated_file);
+        }
+        let count = updated_files.len();
+        if count == 0 {
+            return Ok(0);
+        }
+        debug!("Updating metadata of {:?} files ...", count);
+        for file in updated_files {
+            directory.upsert_file(file);
+        }
+        let _ = try!(self.directory_helper.update(directory));
+        Ok(count)
+    }
+
+    /// Moves all the files in `source` for which `predicate` returns true into the
+    /// `ARCHIVE_DIRECTORY_NAME` sub directory of `source`. The sub directory is created with the
+    /// same versioning and AccessLevel as `source` if it does not exist.
+    /// The archive directory is saved before `source`, so a failure never loses a file.
+    /// Returns the number of files archived
+    pub fn archive<F>(&self, source: &mut DirectoryListing, predicate: F) -> Result<usize, NfsError>
+        where F: Fn(&File) -> bool
+    {
+        let files: Vec<File> = source.get_files()
+                                     .iter()
+                                     .filter(|file| predicate(*file))
+                                     .cloned()
+                                     .collect();
+        if files.is_empty() {
+            return Ok(0);
+        }
+        let mut archive = try!(self.get_or_create_sub_directory(source,
+                                                                ::ARCHIVE_DIRECTORY_NAME));
+        if files.iter().any(|file| archive.find_file(file.get_name()).is_some()) {
+            return Err(NfsError::FileAlreadyExistsWithSameName);
+        }
+
+        debug!("Archiving {:?} files ...", files.len());
+        for file in &files {
+            archive.upsert_file(file.clone());
+        }
+        let _ = try!(self.directory_helper.update(&archive));
+        source.upsert_sub_directory(archive.get_metadata().clone());
+        for file in &files {
+            try!(source.remove_file(file.get_name()));
+        }
+        let _ = try!(self.directory_helper.update(source));
+        Ok(files.len())
+    }
+
+    /// Creates an empty file with a random name in the TEMPORARY_DIRECTORY_NAME sub directory of
+    /// `root`, which expires after `ttl_seconds`. The sub directory is created if it does not
+    /// exist. Expired files are deleted by `gc_temp`.
+    /// Returns (created file, temporary directory)
+    pub fn create_temporary(&self,
+                            root: &mut DirectoryListing,
+                            ttl_seconds: u64)
+                            -> Result<(File, DirectoryListing), NfsError> {
+        let temp_directory = try!(self.get_or_create_sub_directory(root,
+                                                                   ::TEMPORARY_DIRECTORY_NAME));
+        let name = try!(utility::generate_random_vector::<u8>(16)).to_hex();
+        let mut metadata = FileMetadata::new(name.clone(), Vec::new());
+        metadata.set_expiry_time(::time::now_utc() + ::time::Duration::seconds(ttl_seconds as i64));
+        let file = try!(File::new(metadata, DataMap::None));
+
+        debug!("Creating temporary file ...");
+        let writer = Writer::new(self.client.clone(), Mode::Overwrite, temp_directory, file);
+        let (temp_directory, _) = try!(writer.close());
+        root.upsert_sub_directory(temp_directory.get_metadata().clone());
+        let file = try!(temp_directory.find_file(&name).cloned().ok_or(NfsError::FileNotFound));
+        Ok((file, temp_directory))
+    }
+
+    /// Deletes the expired files in the TEMPORARY_DIRECTORY_NAME sub directory of `root`.
+    /// Returns the number of files deleted
+    pub fn gc_temp(&self, root: &mut DirectoryListing) -> Result<usize, NfsError> {
+        let temp_directory_key =
+            match root.find_sub_directory(&::TEMPORARY_DIRECTORY_NAME.to_string()) {
+                Some(metadata) => metadata.get_key().clone(),
+                None => return Ok(0),
+            };
+        let mut temp_directory = try!(self.directory_helper.get(&temp_directory_key));
+        let deleted = try!(self.delete_expired(&mut temp_directory));
+        if deleted > 0 {
+            root.upsert_sub_directory(temp_directory.get_metadata().clone());
+        }
+        Ok(deleted)
+    }
+
+    /// Deletes the files in `parent_directory` whose expiry time is before the current time. A
+    /// file expiring exactly now is kept. The directory is saved only if any file was deleted.
+    /// Returns the number of files deleted
+    pub fn delete_expired(&self,
+                          parent_directory: &mut DirectoryListing)
+                          -> Result<usize, NfsError> {
+        let now = ::time::now_utc();
+        let expired: Vec<String> = parent_directory.get_files()
+                                                   .iter()
+                                                   .filter(|file| {
+                                                       file.get_metadata()
+                                                           .get_expiry_time()
+                                                           .map_or(false,
+                                                                   |expiry| *expiry < now)
+                                                   })
+                                                   .map(|file| file.get_name().clone())
+                                                   .collect();
+        if expired.is_empty() {
+            return Ok(0);
+        }
+
+        debug!("Deleting {:?} expired files ...", expired.len());
+        for name in &expired {
+            try!(parent_directory.remove_file(name));
+        }
+        let _ = try!(self.directory_helper.update(parent_directory));
+        Ok(expired.len())
+    }
+
+    /// Returns the sub directory of `parent_directory` with the given name, creating it with the
+    /// same type tag, versioning and AccessLevel as `parent_directory` if it does not exist
+    fn get_or_create_sub_directory(&self,
+                                   parent_directory: &mut DirectoryListing,
+                                   name: &str)
+                                   -> Result<DirectoryListing, NfsError> {
+        let key = parent_directory.find_sub_directory(&name.to_string())
+                                  .map(|metadata| metadata.get_key().clone());
+        match key {
+            Some(key) => self.directory_helper.get(&key),
+            None => {
+                debug!("Creating {:?} directory ...", name);
+                let type_tag = parent_directory.get_key().get_type_tag();
+                let versioned = parent_directory.get_key().is_versioned();
+                let access_level = parent_directory.get_key().get_access_level().clone();
+                Ok(try!(self.directory_helper.create(name.to_string(),
+                                                     type_tag,
+                                                     Vec::new(),
+                                                     versioned,
+                                                     access_level,
+                                                     Some(parent_directory)))
+                       .0)
+            }
+        }
+    }
+
+    /// Computes the SHA3-256 hash of the content of every file in `directory`, using up to
+    /// `parallelism` threads. Returns a map of file name to hash.
+    /// If the content of some of the files could not be read, `NfsError::PartialFailure` is
+    /// returned holding the number of files hashed and the name and error of each failed file
+    pub fn bulk_checksum(&self,
+                         directory: &DirectoryListing,
+                         parallelism: usize)
+                         -> Result<HashMap<String, [u8; 32]>, NfsError> {
+        if parallelism == 0 {
+            return Err(NfsError::ParameterIsNotValid);
+        }
+        let files = directory.get_files();
+        if files.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let chunk_size = (files.len() + parallelism - 1) / parallelism;
+        debug!("Computing checksums of {:?} files ...", files.len());
+        let handles: Vec<_> = files.chunks(chunk_size)
+                                   .map(|chunk| {
+                                       let client = self.client.clone();
+                                       let chunk = chunk.to_vec();
+                                       thread::spawn(move || {
+                                           let file_helper = FileHelper::new(client);
+                                           chunk.iter()
+                                                .map(|file| {
+                                                    (file.get_name().clone(),
+                                                     file_helper.checksum(file))
+                                                })
+                                                .collect::<Vec<_>>()
+                                       })
+                                   })
+                                   .collect();
+
+        let mut results = Vec::new();
+        for handle in handles {
+            let chunk_results = try!(handle.join().map_err(|_| {
+                NfsError::Unexpected("Checksum thread panicked".to_string())
+            }));
+            results.extend(chunk_results.into_iter().map(|(name, result)| {
+                (name.clone(), result.map(|checksum| (name, checksum)))
+            }));
+        }
+        Ok(try!(utils::partition_results(results)).into_iter().collect())
+    }
+
+    fn checksum(&self, file: &File) -> Result<[u8; 32], NfsError> {
+        let mut reader = self.read(file);
+        let size = reader.size();
+        utils::sha3_256_by_slices(size, |position, length| reader.read(position, length))
     }
 
     /// Helper function to Update content of a file in a directory listing
@@ -93,6 +728,9 @@ impl FileHelper {
     /// can be written to the network
     /// The file is actually saved in the directory listing only after
     /// `writer.close()` is invoked
+    /// Returns `NfsError::FileLocked` if a write lock is held on the file, and
+    /// `NfsError::ConcurrentModification` if the content of the file has been written on the
+    /// network since `file` was fetched
     pub fn update_content(&self,
                           file: File,
                           mode: Mode,
@@ -100,65 +738,609 @@ impl FileHelper {
                           -> Result<Writer, NfsError> {
         {
             let existing_file = try!(parent_directory.find_file(file.get_name())
-                                                     .ok_or(NfsError::FileNotFound));
+                                                     .ok_or_else(|| {
+                                                         NfsError::FileNotFound
+                                                             .with_context(&file.get_name()[..])
+                                                     }));
+            if *existing_file != file {
+                return Err(NfsError::FileDoesNotMatch);
+            }
+            if existing_file.get_metadata().get_lock_token().is_some() {
+                return Err(NfsError::FileLocked);
+            }
+        }
+        try!(self.check_not_modified(&file, &parent_directory));
+        Ok(Writer::new(self.client.clone(), mode, parent_directory, file))
+    }
+
+    /// Fetches the latest version of `parent_directory` from the network and checks that no
+    /// write lock is held on `file` in it and that its version is the same as that of `file`
+    fn check_not_modified(&self,
+                          file: &File,
+                          parent_directory: &DirectoryListing)
+                          -> Result<(), NfsError> {
+        let latest_file = try!(self.get_latest_unlocked(file.get_id(), parent_directory));
+        if latest_file.get_version() == file.get_version() {
+            Ok(())
+        } else {
+            Err(NfsError::ConcurrentModification)
+        }
+    }
+
+    /// Returns the file with id `file_id` from the latest version of `parent_directory` on the
+    /// network. The lock is checked on the network, since `parent_directory` can have been
+    /// fetched before the lock was acquired by another session
+    fn get_latest_unlocked(&self,
+                           file_id: &XorName,
+                           parent_directory: &DirectoryListing)
+                           -> Result<File, NfsError> {
+        let latest_directory = try!(self.directory_helper.get(parent_directory.get_key()));
+        let latest_file = try!(latest_directory.find_file_by_id(file_id)
+                                               .cloned()
+                                               .ok_or(NfsError::ConcurrentModification));
+        if latest_file.get_metadata().get_lock_token().is_some() {
+            return Err(NfsError::FileLocked);
+        }
+        Ok(latest_file)
+    }
+
+    /// Same as `update_content`, for a file on which `lock` is held.
+    /// Returns `NfsError::FileLocked` if `lock` is not the lock held on the file
+    pub fn update_content_with_lock(&self,
+                                    file: File,
+                                    mode: Mode,
+                                    parent_directory: DirectoryListing,
+                                    lock: &WriteLock)
+                                    -> Result<Writer, NfsError> {
+        {
+            let existing_file = try!(parent_directory.find_file(file.get_name())
+                                                     .ok_or_else(|| {
+                                                         NfsError::FileNotFound
+                                                             .with_context(&file.get_name()[..])
+                                                     }));
+            if *existing_file != file {
+                return Err(NfsError::FileDoesNotMatch);
+            }
+            if *file.get_name() != *lock.get_file_name() ||
+               existing_file.get_metadata().get_lock_token() != Some(lock.get_token()) {
+                return Err(NfsError::FileLocked);
+            }
+        }
+        Ok(Writer::new(self.client.clone(), mode, parent_directory, file))
+    }
+
+    /// Acquires a write lock on the file named `file_name`. `directory` is refreshed with its
+    /// latest version from the network before checking for an existing lock, and is updated with
+    /// the lock. Returns `NfsError::FileLocked` if a lock is already held on the file.
+    /// Refer `WriteLock` for details on releasing the lock
+    pub fn lock(&self,
+                file_name: &str,
+                directory: &mut DirectoryListing)
+                -> Result<WriteLock, NfsError> {
+        *directory = try!(self.directory_helper.get(directory.get_key()));
+        let mut file = try!(directory.find_file(&file_name.to_string())
+                                     .cloned()
+                                     .ok_or_else(|| {
+                                         NfsError::FileNotFound.with_context(file_name)
+                                     }));
+        if file.get_metadata().get_lock_token().is_some() {
+            return Err(NfsError::FileLocked);
+        }
+        let token = try!(utility::generate_random_string(32));
+        debug!("Acquiring write lock on file {:?} ...", file_name);
+        file.get_mut_metadata().set_lock_token(Some(token.clone()));
+        directory.upsert_file(file);
+        let _ = try!(self.directory_helper.update(directory));
+        Ok(WriteLock::new(self.client.clone(),
+                          directory.get_key().clone(),
+                          file_name.to_string(),
+                          token))
+    }
+
+    /// Clears the write lock held on the file named `file_name`, whoever holds it. This is meant
+    /// for recovering a file whose lock was never released, eg. because the session holding it
+    /// ended abruptly. `directory` is refreshed with its latest version from the network and is
+    /// updated without the lock. The WriteLock which held the lock no longer has any effect.
+    /// Returns Option<directory's parent>
+    pub fn force_unlock(&self,
+                        file_name: &str,
+                        directory: &mut DirectoryListing)
+                        -> Result<Option<DirectoryListing>, NfsError> {
+        *directory = try!(self.directory_helper.get(directory.get_key()));
+        let mut file = try!(directory.find_file(&file_name.to_string())
+                                     .cloned()
+                                     .ok_or_else(|| {
+                                         NfsError::FileNotFound.with_context(file_name)
+                                     }));
+        if file.get_metadata().get_lock_token().is_none() {
+            return Ok(None);
+        }
+        debug!("Forcibly releasing write lock on file {:?} ...", file_name);
+        file.get_mut_metadata().set_lock_token(None);
+        directory.upsert_file(file);
+        self.directory_helper.update(directory)
+    }
+
+    /// Replaces the bytes of the file starting at `offset` with `data`, keeping the rest of the
+    /// content unchanged. If `offset + data.len()` is beyond the end of the file, the file is
+    /// extended to hold all of `data`.
+    /// Returns the updated parent directory
+    pub fn overwrite_at(&self,
+                        file: File,
+                        offset: u64,
+                        data: &[u8],
+                        parent_directory: DirectoryListing)
+                        -> Result<DirectoryListing, NfsError> {
+        let mut writer = try!(self.update_content(file, Mode::Modify, parent_directory));
+        writer.write(data, offset);
+        Ok(try!(writer.close()).0)
+    }
+
+    /// Updates the content of the file with the decoded `base64_data`. Both the standard and the
+    /// URL safe base64 alphabets are accepted.
+    /// Returns the updated parent directory
+    pub fn update_content_from_base64(&self,
+                                      file: File,
+                                      mode: Mode,
+                                      base64_data: &str,
+                                      parent_directory: DirectoryListing)
+                                      -> Result<DirectoryListing, NfsError> {
+        let data = try!(base64_data.from_base64());
+        let mut writer = try!(self.update_content(file, mode, parent_directory));
+        writer.write(&data, 0);
+        Ok(try!(writer.close()).0)
+    }
+
+    /// Returns a Writer to update the content of `file`, which also replaces the metadata of the
+    /// file with `new_metadata` when closed. The size and modified time are set on close as usual
+    /// and the created time of `file` is retained.
+    /// Returns `NfsError::FileLocked` if a write lock is held on the file, and
+    /// `NfsError::ConcurrentModification` if the content of the file has been written on the
+    /// network since `file` was fetched.
+    /// The parent directory is updated only once, on close of the Writer
+    pub fn update_content_and_metadata(&self,
+                                       mut file: File,
+                                       mode: Mode,
+                                       new_metadata: FileMetadata,
+                                       parent_directory: DirectoryListing)
+                                       -> Result<Writer, NfsError> {
+        {
+            let existing_file = try!(parent_directory.find_file(file.get_name())
+                                                     .ok_or_else(|| {
+                                                         NfsError::FileNotFound
+                                                             .with_context(&file.get_name()[..])
+                                                     }));
             if *existing_file != file {
                 return Err(NfsError::FileDoesNotMatch);
             }
+            if new_metadata.get_name() != file.get_name() &&
+               parent_directory.find_file(new_metadata.get_name()).is_some() {
+                return Err(NfsError::FileAlreadyExistsWithSameName);
+            }
         }
+        try!(self.check_not_modified(&file, &parent_directory));
+        let created_time = file.get_metadata().get_created_time().clone();
+        *file.get_mut_metadata() = new_metadata;
+        file.get_mut_metadata().set_created_time(created_time);
         Ok(Writer::new(self.client.clone(), mode, parent_directory, file))
     }
 
+    /// Restores the content of `original_file` in `parent_directory` from the local copy at
+    /// `local_path`. The size of the local copy must match the size of `original_file`.
+    /// The id and metadata of the file are retained, except for the modified time.
+    /// Returns Option<parent_directory's parent>
+    pub fn restore_from_local_backup(&self,
+                                     local_path: &Path,
+                                     original_file: File,
+                                     parent_directory: &mut DirectoryListing)
+                                     -> Result<Option<DirectoryListing>, NfsError> {
+        let mut content = Vec::new();
+        let _ = try!(try!(fs::File::open(local_path)).read_to_end(&mut content));
+        if content.len() as u64 != original_file.get_size() {
+            return Err(NfsError::FileDoesNotMatch);
+        }
+
+        debug!("Restoring file content from local backup ...");
+        let mut writer = try!(self.update_content(original_file,
+                                                  Mode::Overwrite,
+                                                  parent_directory.clone()));
+        writer.write(&content, 0);
+        let (updated_directory, grand_parent) = try!(writer.close());
+        *parent_directory = updated_directory;
+        Ok(grand_parent)
+    }
+
+    /// Encrypts the metadata of `file` with `key` so that it is opaque to the Network. The
+    /// encrypted metadata is held in the File and its plain metadata is cleared, except for the
+    /// name which is replaced by the hex encoded id of the file, so that it stays unique within
+    /// the parent directory.
+    /// XSalsa20-Poly1305 (`secretbox`) is used in place of AES-GCM, since sodiumoxide does not
+    /// provide AES-GCM. Both are authenticated ciphers with 256 bit keys
+    pub fn encrypt_metadata(&self, file: &mut File, key: &[u8; 32]) -> Result<(), NfsError> {
+        if file.is_metadata_encrypted() {
+            return Err(NfsError::ParameterIsNotValid);
+        }
+        let serialised_metadata = try!(serialise(file.get_metadata()));
+        let nonce = secretbox::gen_nonce();
+        let mut encrypted_metadata = nonce.0.to_vec();
+        encrypted_metadata.extend(secretbox::seal(&serialised_metadata,
+                                                  &nonce,
+                                                  &secretbox::Key(*key)));
+        file.set_encrypted_metadata(encrypted_metadata);
+        let placeholder_name = file.get_id().0[..].to_hex();
+        *file.get_mut_metadata() = FileMetadata::new(placeholder_name, Vec::new());
+        Ok(())
+    }
+
+    /// Decrypts the metadata of `file` encrypted using `encrypt_metadata` and restores it
+    pub fn decrypt_metadata(&self, file: &mut File, key: &[u8; 32]) -> Result<(), NfsError> {
+        if !file.is_metadata_encrypted() {
+            return Err(NfsError::ParameterIsNotValid);
+        }
+        let metadata = {
+            let encrypted_metadata = file.get_encrypted_metadata();
+            if encrypted_metadata.len() < secretbox::NONCEBYTES {
+                return Err(NfsError::from(CoreError::SymmetricDecipherFailure));
+            }
+            let (nonce, cipher_text) = encrypted_metadata.split_at(secretbox::NONCEBYTES);
+            let nonce = try!(secretbox::Nonce::from_slice(nonce)
+                                 .ok_or(CoreError::SymmetricDecipherFailure));
+            let serialised_metadata = try!(secretbox::open(cipher_text,
+                                                           &nonce,
+                                                           &secretbox::Key(*key))
+                                               .map_err(|()| CoreError::SymmetricDecipherFailure));
+            try!(deserialise::<FileMetadata>(&serialised_metadata))
+        };
+        *file.get_mut_metadata() = metadata;
+        file.set_encrypted_metadata(Vec::new());
+        Ok(())
+    }
 
-    /// Return the versions of a directory containing modified versions of a file
+    /// Return the versions of a directory containing modified versions of a file, newest first.
+    /// If `limit` is specified, at most `limit` versions are returned and older versions of the
+    /// directory are not fetched from the network
     pub fn get_versions(&self,
                         file: &File,
-                        parent_directory: &DirectoryListing)
+                        parent_directory: &DirectoryListing,
+                        limit: Option<usize>)
                         -> Result<Vec<File>, NfsError> {
-        let mut versions = Vec::<File>::new();
-        let directory_helper = DirectoryHelper::new(self.client.clone());
+        Ok(try!(self.get_version_history(file, parent_directory, limit))
+               .into_iter()
+               .map(|version| version.file)
+               .collect())
+    }
+
+    /// Return the versions of a file as explained for `get_versions`, each along with the modified
+    /// time and the index of the directory version which introduced it
+    pub fn get_version_history(&self,
+                               file: &File,
+                               parent_directory: &DirectoryListing,
+                               limit: Option<usize>)
+                               -> Result<Vec<FileVersion>, NfsError> {
+        let mut versions = Vec::<FileVersion>::new();
 
-        let sdv_versions = try!(directory_helper.get_versions(parent_directory.get_key().get_id(),
-                                                              parent_directory.get_key()
-                                                                              .get_type_tag()));
+        let directory_key = parent_directory.get_key();
+        let sdv_versions = try!(self.directory_helper.get_versions(directory_key.get_id(),
+                                                                   directory_key.get_type_tag()));
         let mut modified_time = ::time::empty_tm();
-        for version_id in sdv_versions {
+        for (version_index, version_id) in sdv_versions.into_iter().enumerate().rev() {
             let directory_listing =
-                try!(directory_helper.get_by_version(parent_directory.get_key().get_id(),
-                                                     parent_directory.get_key()
-                                                                     .get_access_level(),
-                                                     version_id.clone()));
-            if let Some(file) = directory_listing.get_files().iter().find(|&entry| {
-                entry.get_name() == file.get_name()
-            }) {
-                if *file.get_metadata().get_modified_time() != modified_time {
-                    modified_time = file.get_metadata().get_modified_time().clone();
-                    versions.push(file.clone());
+                try!(self.directory_helper.get_by_version(directory_key.get_id(),
+                                                          directory_key.get_access_level(),
+                                                          version_id.clone()));
+            if let Some(file) = directory_listing.find_file(file.get_name()) {
+                let directory_modified_time = directory_listing.get_metadata()
+                                                               .get_modified_time()
+                                                               .clone();
+                if *file.get_modified_time() == modified_time {
+                    // The version is also present in this older directory version, so it was
+                    // introduced by this one or an even older one
+                    if let Some(version) = versions.last_mut() {
+                        version.directory_modified_time = directory_modified_time;
+                        version.version_index = version_index;
+                    }
+                    continue;
                 }
+                if limit.map_or(false, |limit| versions.len() >= limit) {
+                    break;
+                }
+                modified_time = file.get_modified_time().clone();
+                versions.push(FileVersion {
+                    file: file.clone(),
+                    directory_modified_time: directory_modified_time,
+                    version_index: version_index,
+                });
             }
         }
         Ok(versions)
     }
 
+    /// Removes the history of the file beyond its `keep` newest versions, by removing the versions
+    /// of the parent directory which are older than the one introducing the oldest kept version.
+    /// The history of the other files in the directory is truncated at the same point.
+    /// `keep` must be at least 1. Returns the number of file versions pruned
+    pub fn prune_versions(&self,
+                          file: &File,
+                          parent_directory: &DirectoryListing,
+                          keep: usize)
+                          -> Result<usize, NfsError> {
+        if keep == 0 {
+            return Err(NfsError::ParameterIsNotValid);
+        }
+        let history = try!(self.get_version_history(file, parent_directory, None));
+        if history.len() <= keep {
+            return Ok(0);
+        }
+        let _ = try!(self.directory_helper
+                         .remove_versions_before(parent_directory.get_key(),
+                                                 history[keep - 1].version_index));
+        Ok(history.len() - keep)
+    }
+
+    /// Return the versions of the file whose modified time lies between `start_time` and
+    /// `end_time`, both inclusive, oldest first
+    pub fn get_versions_in_range(&self,
+                                 file: &File,
+                                 parent_directory: &DirectoryListing,
+                                 start_time: ::time::Tm,
+                                 end_time: ::time::Tm)
+                                 -> Result<Vec<File>, NfsError> {
+        if start_time > end_time {
+            return Err(NfsError::ParameterIsNotValid);
+        }
+        Ok(try!(self.get_versions(file, parent_directory, None))
+               .into_iter()
+               .rev()
+               .filter(|version| {
+                   let modified_time = *version.get_modified_time();
+                   modified_time >= start_time && modified_time <= end_time
+               })
+               .collect())
+    }
+
+    /// Reads the chunk at `chunk_index` of the content of the file, when the content is split into
+    /// chunks of `chunk_size` bytes. The last chunk can be shorter than `chunk_size`.
+    /// Returns `NfsError::InvalidRangeSpecified` if the chunk starts beyond the end of the file
+    pub fn read_chunk(&self,
+                      file: &File,
+                      chunk_index: usize,
+                      chunk_size: u64)
+                      -> Result<Vec<u8>, NfsError> {
+        if chunk_size == 0 {
+            return Err(NfsError::ParameterIsNotValid);
+        }
+        let mut reader = self.read(file);
+        let size = reader.size();
+        let offset = try!((chunk_index as u64)
+                              .checked_mul(chunk_size)
+                              .ok_or(NfsError::InvalidRangeSpecified));
+        if offset >= size {
+            return Err(NfsError::InvalidRangeSpecified);
+        }
+        reader.read(offset, ::std::cmp::min(chunk_size, size - offset))
+    }
+
+    /// Returns the content of the file as it was in the version at `version_index` of the list
+    /// returned by `get_versions`, counted from the oldest version
+    pub fn read_at_version(&self,
+                           file: &File,
+                           parent_directory: &DirectoryListing,
+                           version_index: usize)
+                           -> Result<Vec<u8>, NfsError> {
+        let versions = try!(self.get_versions(file, parent_directory, None));
+        let version = try!(versions.iter()
+                                   .rev()
+                                   .nth(version_index)
+                                   .ok_or(NfsError::VersionNotFound));
+        let mut reader = self.read(version);
+        let size = reader.size();
+        reader.read(0, size)
+    }
+
+    /// Returns the change statistics of a file computed from its versions
+    pub fn get_history_summary(&self,
+                               file: &File,
+                               parent_directory: &DirectoryListing)
+                               -> Result<FileSummary, NfsError> {
+        let versions = try!(self.get_versions(file, parent_directory, None));
+        let first = try!(versions.last().ok_or(NfsError::FileNotFound));
+        let last = try!(versions.first().ok_or(NfsError::FileNotFound));
+        let sizes: Vec<u64> = versions.iter().map(|version| version.get_size()).collect();
+        Ok(FileSummary {
+            version_count: versions.len(),
+            first_created: first.get_metadata().get_created_time().clone(),
+            last_modified: last.get_modified_time().clone(),
+            min_size: sizes.iter().cloned().min().unwrap_or(0),
+            max_size: sizes.iter().cloned().max().unwrap_or(0),
+            current_size: last.get_size(),
+        })
+    }
+
+    /// Returns true if the MIME type of the file matches `mime`. `mime` can end in a wildcard
+    /// subtype, eg., `image/*` matches any file whose MIME type starts with `image/`.
+    /// Files without a MIME type never match
+    pub fn content_type_matches(file: &File, mime: &str) -> bool {
+        file.get_metadata().get_mime_type().map_or(false, |mime_type| {
+            if mime.ends_with("/*") {
+                mime_type.starts_with(&mime[..mime.len() - 1])
+            } else {
+                mime_type == mime
+            }
+        })
+    }
+
+    /// Returns the files in the directory whose MIME type matches `mime`. Refer
+    /// `content_type_matches` for the matching rules
+    pub fn filter_by_content_type<'a>(directory: &'a DirectoryListing,
+                                      mime: &str)
+                                      -> Vec<&'a File> {
+        directory.get_files()
+                 .iter()
+                 .filter(|file| FileHelper::content_type_matches(file, mime))
+                 .collect()
+    }
+
+    /// Returns true if the two files have the same content. Self-encryption is convergent, so the
+    /// same content always produces the same data-map and the data-maps can be compared without
+    /// reading anything from the network. Files whose data-map is encrypted for a recipient are
+    /// never considered identical
+    pub fn are_content_identical(a: &File, b: &File) -> bool {
+        !a.get_metadata().is_recipient_encrypted() && !b.get_metadata().is_recipient_encrypted() &&
+        a.get_datamap() == b.get_datamap()
+    }
+
+    /// Groups the files in the directory which have identical content, as determined by
+    /// `are_content_identical`. Only groups of two or more files are returned, in the order in
+    /// which their first file appears in the directory
+    pub fn find_duplicates<'a>(&self, directory: &'a DirectoryListing) -> Vec<Vec<&'a File>> {
+        let mut groups: Vec<Vec<&'a File>> = Vec::new();
+        for file in directory.get_files() {
+            match groups.iter()
+                        .position(|group| FileHelper::are_content_identical(group[0], file)) {
+                Some(index) => groups[index].push(file),
+                None => groups.push(vec![file]),
+            }
+        }
+        groups.into_iter().filter(|group| group.len() > 1).collect()
+    }
+
     /// Returns a reader for reading the file contents
     pub fn read<'a>(&self, file: &'a File) -> Reader<'a> {
         Reader::new(self.client.clone(), file)
     }
+
+    /// Encrypts the data-map of the file for the recipient owning `recipient_public_key`.
+    /// The encrypted data-map, prefixed with the random nonce used, is stored as the user metadata
+    /// of the file and the file is marked as recipient encrypted. The size and checksum of the
+    /// file are cleared, as the file no longer refers to the content. The recipient can retrieve
+    /// the data-map using `decrypt_datamap`.
+    /// Returns `NfsError::ParameterIsNotValid` if the file is already recipient encrypted or has
+    /// user metadata, which would be lost, and `NfsError::ConcurrentModification` if the file was
+    /// modified by another session since it was fetched.
+    /// Returns the updated parent_directory
+    pub fn encrypt_for_recipient(&self,
+                                 mut file: File,
+                                 recipient_public_key: &[u8],
+                                 mut parent_directory: DirectoryListing)
+                                 -> Result<DirectoryListing, NfsError> {
+        if parent_directory.find_file_by_id(file.get_id()).is_none() {
+            return Err(NfsError::FileNotFound);
+        }
+        if file.get_metadata().is_recipient_encrypted() ||
+           !file.get_metadata().get_user_metadata().is_empty() {
+            return Err(NfsError::ParameterIsNotValid);
+        }
+        try!(self.check_not_modified(&file, &parent_directory));
+        let recipient_public_key = try!(box_::PublicKey::from_slice(recipient_public_key)
+                                            .ok_or(NfsError::ParameterIsNotValid));
+        let secret_key = try!(unwrap_result!(self.client.lock()).get_secret_encryption_key())
+                             .clone();
+        let nonce = box_::gen_nonce();
+        let serialised_datamap = try!(serialise(file.get_datamap()));
+        debug!("Encrypting data-map of {:?} file for recipient ...",
+               file.get_name());
+        let mut encrypted_datamap = nonce.0.to_vec();
+        encrypted_datamap.extend(try!(utility::hybrid_encrypt(&serialised_datamap,
+                                                              &nonce,
+                                                              &recipient_public_key,
+                                                              &secret_key)));
+        file.get_mut_metadata().set_user_metadata(encrypted_datamap);
+        file.get_mut_metadata().set_recipient_encrypted(true);
+        file.get_mut_metadata().set_size(0);
+        file.get_mut_metadata().set_checksum(None);
+        file.set_datamap(DataMap::None);
+        file.increment_version();
+        parent_directory.upsert_file(file);
+        let _ = try!(self.directory_helper.update(&parent_directory));
+        Ok(parent_directory)
+    }
+
+    /// Decrypts the data-map of a file which was encrypted for this client using
+    /// `encrypt_for_recipient`. `sender_public_key` is the public encryption key of the client
+    /// which encrypted the file
+    pub fn decrypt_datamap(&self,
+                           file: &File,
+                           sender_public_key: &[u8])
+                           -> Result<DataMap, NfsError> {
+        if !file.get_metadata().is_recipient_encrypted() {
+            return Err(NfsError::ParameterIsNotValid);
+        }
+        let sender_public_key = try!(box_::PublicKey::from_slice(sender_public_key)
+                                         .ok_or(NfsError::ParameterIsNotValid));
+        let secret_key = try!(unwrap_result!(self.client.lock()).get_secret_encryption_key())
+                             .clone();
+        let encrypted_datamap = file.get_metadata().get_user_metadata();
+        if encrypted_datamap.len() < box_::NONCEBYTES {
+            return Err(NfsError::from(CoreError::AsymmetricDecipherFailure));
+        }
+        let (nonce, cipher_text) = encrypted_datamap.split_at(box_::NONCEBYTES);
+        let nonce = try!(box_::Nonce::from_slice(nonce)
+                             .ok_or(CoreError::AsymmetricDecipherFailure));
+        debug!("Decrypting data-map of {:?} file ...", file.get_name());
+        let serialised_datamap = try!(utility::hybrid_decrypt(cipher_text,
+                                                              &nonce,
+                                                              &sender_public_key,
+                                                              &secret_key));
+        Ok(try!(deserialise(&serialised_datamap)))
+    }
 }
 
 #[cfg(test)]
 mod test {
     use std::sync::{Arc, Mutex};
+    use directory_listing::DirectoryListing;
+    use errors::NfsError;
+    use file::File;
     use helper::directory_helper::DirectoryHelper;
-    use helper::file_helper::FileHelper;
+    use helper::file_helper::{FileHelper, FileVersion};
     use helper::writer::Mode;
+    use metadata::file_metadata::FileMetadata;
     use safe_core::client::Client;
     use safe_core::utility::test_utils;
+    use self_encryption::DataMap;
+    use utils;
+    use utils::test::{fill_file, generate_random_file_name};
 
     fn get_client() -> Arc<Mutex<Client>> {
         let test_client = unwrap_result!(test_utils::get_client());
         Arc::new(Mutex::new(test_client))
     }
 
+    fn create_directory(client: Arc<Mutex<Client>>) -> DirectoryListing {
+        let dir_helper = DirectoryHelper::new(client);
+        let (directory, _) = unwrap_result!(dir_helper.create("DirName".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        directory
+    }
+
+    fn create_unversioned_directory(client: Arc<Mutex<Client>>) -> DirectoryListing {
+        let dir_helper = DirectoryHelper::new(client);
+        let (directory, _) = unwrap_result!(dir_helper.create("DirName".to_string(),
+                                                            ::UNVERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            false,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        directory
+    }
+
+    fn create_file(file_helper: &FileHelper,
+                   name: &str,
+                   content: &[u8],
+                   directory: DirectoryListing)
+                   -> DirectoryListing {
+        let mut writer = unwrap_result!(file_helper.create(name.to_string(),
+                                                           Vec::new(),
+                                                           directory));
+        writer.write(content, 0);
+        let (directory, _) = unwrap_result!(writer.close());
+        directory
+    }
+
     #[test]
     fn file_crud() {
         let client = get_client();
@@ -224,8 +1406,17 @@ mod test {
             // versions
             let file = unwrap_option!(directory.find_file(&file_name).map(|file| file.clone()),
                                       "File not found");
-            let versions = unwrap_result!(file_helper.get_versions(&file, &directory));
+            let versions = unwrap_result!(file_helper.get_versions(&file, &directory, None));
             assert_eq!(versions.len(), 3);
+            let latest = unwrap_result!(file_helper.get_versions(&file, &directory, Some(1)));
+            assert_eq!(latest.len(), 1);
+            assert_eq!(latest[0], versions[0]);
+            assert_eq!(latest[0], file);
+            assert_eq!(unwrap_result!(file_helper.read_at_version(&file, &directory, 0)),
+                       vec![0u8; 100]);
+            assert_eq!(unwrap_result!(file_helper.read_at_version(&file, &directory, 1)),
+                       vec![1u8; 50]);
+            assert!(file_helper.read_at_version(&file, &directory, 3).is_err());
         }
         {
             // Update Metadata
@@ -243,4 +1434,1729 @@ mod test {
             assert!(directory.find_file(&file_name).is_none());
         }
     }
+
+    #[test]
+    fn create_with_tags() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let directory = create_directory(client.clone());
+        let tags = vec!["work".to_string(), "draft".to_string()];
+
+        assert!(file_helper.create_with_tags("hello.txt".to_string(),
+                                             Vec::new(),
+                                             vec![String::new()],
+                                             directory.clone())
+                           .is_err());
+        assert!(file_helper.create_with_tags("hello.txt".to_string(),
+                                             Vec::new(),
+                                             vec![::std::iter::repeat('a').take(65).collect()],
+                                             directory.clone())
+                           .is_err());
+
+        let mut writer = unwrap_result!(file_helper.create_with_tags("hello.txt".to_string(),
+                                                                     Vec::new(),
+                                                                     tags.clone(),
+                                                                     directory));
+        writer.write(&[0u8; 10], 0);
+        let (directory, _) = unwrap_result!(writer.close());
+        let file = unwrap_option!(directory.find_file(&"hello.txt".to_string()),
+                                  "File not found");
+        assert_eq!(*file.get_metadata().get_tags(), tags);
+    }
+
+    #[test]
+    fn filter_by_content_type() {
+        let mut directory = unwrap_result!(DirectoryListing::new("DirName".to_string(),
+                                                                 10,
+                                                                 Vec::new(),
+                                                                 true,
+                                                                 ::AccessLevel::Private,
+                                                                 None));
+        for &(name, mime_type) in &[("a.png", Some("image/png")),
+                                    ("b.jpg", Some("image/jpeg")),
+                                    ("c.txt", Some("text/plain")),
+                                    ("d", None)] {
+            let mut metadata = FileMetadata::new(name.to_string(), Vec::new());
+            if let Some(mime_type) = mime_type {
+                metadata.set_mime_type(mime_type.to_string());
+            }
+            directory.upsert_file(unwrap_result!(File::new(metadata, DataMap::None)));
+        }
+
+        let file = unwrap_option!(directory.find_file(&"a.png".to_string()), "File not found");
+        assert!(FileHelper::content_type_matches(file, "image/png"));
+        assert!(FileHelper::content_type_matches(file, "image/*"));
+        assert!(!FileHelper::content_type_matches(file, "image/jpeg"));
+        assert!(!FileHelper::content_type_matches(file, "text/*"));
+        let file = unwrap_option!(directory.find_file(&"d".to_string()), "File not found");
+        assert!(!FileHelper::content_type_matches(file, "image/*"));
+
+        assert_eq!(FileHelper::filter_by_content_type(&directory, "image/*").len(), 2);
+        assert_eq!(FileHelper::filter_by_content_type(&directory, "text/plain").len(), 1);
+        assert!(FileHelper::filter_by_content_type(&directory, "video/*").is_empty());
+    }
+
+    #[test]
+    fn bulk_update_metadata() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let mut directory = create_directory(client.clone());
+        directory = create_file(&file_helper, "small.txt", &[0u8; 10], directory);
+        directory = create_file(&file_helper, "large.txt", &[0u8; 100], directory);
+        directory = create_file(&file_helper, "larger.txt", &[0u8; 200], directory);
+
+        let filter = |file: &File| file.get_size() > 50;
+        let update = |metadata: &mut FileMetadata| metadata.set_user_metadata(vec![1u8]);
+        let count = unwrap_result!(file_helper.bulk_update_metadata(&mut directory,
+                                                                    filter,
+                                                                    update));
+        assert_eq!(count, 2);
+        let directory = unwrap_result!(DirectoryHelper::new(client.clone())
+                                           .get(directory.get_key()));
+        for file in directory.get_files() {
+            let expected = if file.get_size() > 50 {
+                vec![1u8]
+            } else {
+                Vec::new()
+            };
+            assert_eq!(*file.get_metadata().get_user_metadata(), expected);
+        }
+    }
+
+    #[test]
+    fn archive_files() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let mut directory = create_directory(client.clone());
+        directory = create_file(&file_helper, "small.txt", &[0u8; 10], directory);
+        directory = create_file(&file_helper, "large.txt", &[0u8; 100], directory);
+
+        let is_large = |file: &File| file.get_size() > 50;
+        assert_eq!(unwrap_result!(file_helper.archive(&mut directory, &is_large)), 1);
+        assert!(directory.find_file(&"large.txt".to_string()).is_none());
+        assert!(directory.find_file(&"small.txt".to_string()).is_some());
+        // Nothing left to archive
+        assert_eq!(unwrap_result!(file_helper.archive(&mut directory, &is_large)), 0);
+
+        let directory = unwrap_result!(dir_helper.get(directory.get_key()));
+        let archive_metadata =
+            unwrap_option!(directory.find_sub_directory(&::ARCHIVE_DIRECTORY_NAME.to_string()),
+                           "Archive directory not found");
+        assert_eq!(archive_metadata.get_access_level(),
+                   directory.get_key().get_access_level());
+        let archive = unwrap_result!(dir_helper.get(archive_metadata.get_key()));
+        assert!(archive.find_file(&"large.txt".to_string()).is_some());
+        assert_eq!(archive.get_files().len(), 1);
+    }
+
+    #[test]
+    fn bulk_checksum() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let mut directory = create_directory(client.clone());
+        directory = create_file(&file_helper, "a.txt", &[1u8; 10], directory);
+        directory = create_file(&file_helper, "b.txt", &[2u8; 10], directory);
+        directory = create_file(&file_helper, "c.txt", &[1u8; 10], directory);
+
+        assert!(file_helper.bulk_checksum(&directory, 0).is_err());
+        let checksums = unwrap_result!(file_helper.bulk_checksum(&directory, 2));
+        assert_eq!(checksums.len(), 3);
+        assert_eq!(checksums.get("a.txt"), checksums.get("c.txt"));
+        assert!(checksums.get("a.txt") != checksums.get("b.txt"));
+        assert_eq!(checksums.get("b.txt"), Some(&utils::sha3_256(&[2u8; 10])));
+        assert_eq!(unwrap_result!(file_helper.bulk_checksum(&directory, 8)), checksums);
+    }
+
+    #[test]
+    fn restore_from_local_backup() {
+        use std::fs;
+        use std::io::Write;
+
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let mut directory = create_directory(client.clone());
+        directory = create_file(&file_helper, "hello.txt", &[0u8; 10], directory);
+        let file = unwrap_option!(directory.find_file(&"hello.txt".to_string()),
+                                  "File not found")
+                       .clone();
+
+        let backup_path = ::std::env::temp_dir().join(format!("safe_nfs_backup_{}",
+                                                              file.get_id().as_hex()));
+        unwrap_result!(unwrap_result!(fs::File::create(&backup_path)).write_all(&[1u8; 10]));
+        let _ = unwrap_result!(file_helper.restore_from_local_backup(&backup_path,
+                                                                     file.clone(),
+                                                                     &mut directory));
+        let restored_file = unwrap_option!(directory.find_file(&"hello.txt".to_string()),
+                                           "File not found")
+                                .clone();
+        assert_eq!(restored_file.get_id(), file.get_id());
+        let mut reader = file_helper.read(&restored_file);
+        let size = reader.size();
+        assert_eq!(unwrap_result!(reader.read(0, size)), vec![1u8; 10]);
+
+        // Local copy of a different size is rejected
+        unwrap_result!(unwrap_result!(fs::File::create(&backup_path)).write_all(&[1u8; 5]));
+        assert!(file_helper.restore_from_local_backup(&backup_path, restored_file, &mut directory)
+                           .is_err());
+        unwrap_result!(fs::remove_file(&backup_path));
+    }
+
+    #[test]
+    fn encrypt_and_decrypt_metadata() {
+        use rustc_serialize::hex::ToHex;
+
+        let file_helper = FileHelper::new(get_client());
+        let mut metadata = FileMetadata::new("secret.txt".to_string(), vec![1u8, 2, 3]);
+        metadata.set_tags(vec!["private".to_string()]);
+        let original_file = unwrap_result!(File::new(metadata, DataMap::None));
+        let key = [7u8; 32];
+
+        let mut file = original_file.clone();
+        assert!(file_helper.decrypt_metadata(&mut file, &key).is_err());
+        unwrap_result!(file_helper.encrypt_metadata(&mut file, &key));
+        assert!(file.is_metadata_encrypted());
+        assert_eq!(*file.get_name(), file.get_id().0[..].to_hex());
+        assert!(file.get_metadata().get_user_metadata().is_empty());
+        assert!(file_helper.encrypt_metadata(&mut file, &key).is_err());
+
+        // Files with encrypted metadata do not clash by name
+        let metadata = FileMetadata::new("other.txt".to_string(), Vec::new());
+        let mut other_file = unwrap_result!(File::new(metadata, DataMap::None));
+        unwrap_result!(file_helper.encrypt_metadata(&mut other_file, &key));
+        assert!(other_file.get_name() != file.get_name());
+
+        let mut wrong_key_file = file.clone();
+        assert!(file_helper.decrypt_metadata(&mut wrong_key_file, &[8u8; 32]).is_err());
+
+        unwrap_result!(file_helper.decrypt_metadata(&mut file, &key));
+        assert!(!file.is_metadata_encrypted());
+        assert_eq!(file, original_file);
+    }
+
+    #[test]
+    fn update_content_and_metadata() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let mut directory = create_directory(client.clone());
+        directory = create_file(&file_helper, "draft.txt", &[0u8; 10], directory);
+        directory = create_file(&file_helper, "other.txt", &[0u8; 10], directory);
+        let file = unwrap_option!(directory.find_file(&"draft.txt".to_string()), "File not found")
+                       .clone();
+
+        let mut new_metadata = file.get_metadata().clone();
+        new_metadata.set_name("other.txt".to_string());
+        assert!(file_helper.update_content_and_metadata(file.clone(),
+                                                        Mode::Overwrite,
+                                                        new_metadata,
+                                                        directory.clone())
+                           .is_err());
+
+        let mut new_metadata = file.get_metadata().clone();
+        new_metadata.set_name("final.txt".to_string());
+        new_metadata.set_user_metadata(vec![1u8]);
+        let mut writer = unwrap_result!(file_helper.update_content_and_metadata(file.clone(),
+                                                                                Mode::Overwrite,
+                                                                                new_metadata,
+                                                                                directory));
+        writer.write(&[1u8; 20], 0);
+        let (directory, _) = unwrap_result!(writer.close());
+
+        assert!(directory.find_file(&"draft.txt".to_string()).is_none());
+        let updated_file = unwrap_option!(directory.find_file(&"final.txt".to_string()),
+                                          "File not found");
+        assert_eq!(updated_file.get_id(), file.get_id());
+        assert_eq!(*updated_file.get_metadata().get_user_metadata(), vec![1u8]);
+        assert_eq!(updated_file.get_size(), 20);
+        let mut reader = file_helper.read(updated_file);
+        assert_eq!(unwrap_result!(reader.read(0, 20)), vec![1u8; 20]);
+    }
+
+    #[test]
+    fn created_time_is_retained() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let mut directory = create_directory(client.clone());
+        directory = create_file(&file_helper, "created.txt", &[0u8; 10], directory);
+        let file = unwrap_option!(directory.find_file(&"created.txt".to_string()),
+                                  "File not found")
+                       .clone();
+        let created_time = file.get_metadata().get_created_time().clone();
+        let modified_time = file.get_modified_time().clone();
+
+        ::std::thread::sleep(::std::time::Duration::from_millis(10));
+        let new_metadata = FileMetadata::new("created.txt".to_string(), vec![1u8]);
+        let mut writer = unwrap_result!(file_helper.update_content_and_metadata(file,
+                                                                                Mode::Overwrite,
+                                                                                new_metadata,
+                                                                                directory));
+        writer.write(&[1u8; 20], 0);
+        let (mut directory, _) = unwrap_result!(writer.close());
+        let mut file = unwrap_option!(directory.find_file(&"created.txt".to_string()),
+                                      "File not found")
+                           .clone();
+        assert_eq!(*file.get_metadata().get_created_time(), created_time);
+        assert!(*file.get_modified_time() > modified_time);
+
+        *file.get_mut_metadata() = FileMetadata::new("created.txt".to_string(), vec![2u8]);
+        let _ = unwrap_result!(file_helper.update_metadata(file, &mut directory));
+        let file = unwrap_option!(directory.find_file(&"created.txt".to_string()),
+                                  "File not found");
+        assert_eq!(*file.get_metadata().get_created_time(), created_time);
+        assert_eq!(*file.get_metadata().get_user_metadata(), vec![2u8]);
+    }
+
+    #[test]
+    fn zero_out() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let mut directory = create_directory(client.clone());
+        directory = create_file(&file_helper, "secret.txt", &[9u8; 100], directory);
+        let file = unwrap_option!(directory.find_file(&"secret.txt".to_string()),
+                                  "File not found")
+                       .clone();
+
+        let directory = unwrap_result!(file_helper.zero_out(file.clone(), directory));
+        assert!(directory.find_file(file.get_name()).is_none());
+
+        // The last version of the file before deletion holds only zeros
+        let versions = unwrap_result!(file_helper.get_versions(&file, &directory, None));
+        let last_version = unwrap_option!(versions.first(), "No versions found");
+        assert!(last_version.get_datamap() != file.get_datamap());
+        let mut reader = file_helper.read(last_version);
+        assert_eq!(unwrap_result!(reader.read(0, 100)), vec![0u8; 100]);
+    }
+
+    #[test]
+    fn writer_expected_hash() {
+        use std::io::Write;
+
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let directory = create_directory(client.clone());
+        let content = [3u8; 64];
+
+        let mut writer = unwrap_result!(file_helper.create("bad.txt".to_string(),
+                                                           Vec::new(),
+                                                           directory.clone()));
+        writer.expected_hash(utils::sha3_256(&[4u8; 64]));
+        writer.write(&content, 0);
+        match writer.close() {
+            Err(NfsError::IntegrityCheckFailed) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        let mut writer = unwrap_result!(file_helper.create("good.txt".to_string(),
+                                                           Vec::new(),
+                                                           directory));
+        writer.expected_hash(utils::sha3_256(&content));
+        writer.write(&content[32..], 32);
+        writer.write(&content[..32], 0);
+        let (directory, _) = unwrap_result!(writer.close());
+        assert!(directory.find_file(&"good.txt".to_string()).is_some());
+
+        // Sequential writes are hashed as they are written
+        let mut writer = unwrap_result!(file_helper.create("sequential.txt".to_string(),
+                                                           Vec::new(),
+                                                           directory.clone()));
+        writer.expected_hash(utils::sha3_256(&content));
+        unwrap_result!(writer.write_all(&content[..32]));
+        unwrap_result!(writer.write_all(&content[32..]));
+        let (directory, _) = unwrap_result!(writer.close());
+        let file = unwrap_option!(directory.find_file(&"sequential.txt".to_string()),
+                                  "File not found");
+        assert_eq!(file.get_metadata().get_checksum(), Some(&utils::sha3_256(&content)));
+
+        let mut writer = unwrap_result!(file_helper.create("truncated.txt".to_string(),
+                                                           Vec::new(),
+                                                           directory));
+        writer.expected_hash(utils::sha3_256(&content[..32]));
+        unwrap_result!(writer.write_all(&content));
+        unwrap_result!(writer.truncate(32));
+        assert!(writer.close().is_ok());
+    }
+
+    #[test]
+    fn migrate_format() {
+        use metadata::file_metadata::CURRENT_SCHEMA_VERSION;
+
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let mut directory = create_directory(client.clone());
+        directory = create_file(&file_helper, "old.txt", &[0u8; 10], directory);
+        let mut file = unwrap_option!(directory.find_file(&"old.txt".to_string()),
+                                      "File not found")
+                           .clone();
+        file.get_mut_metadata().set_schema_version(0);
+        file.get_mut_metadata().set_tags(vec!["valid".to_string(), String::new()]);
+        let _ = unwrap_result!(file_helper.update_metadata(file.clone(), &mut directory));
+
+        // Dry run only changes the local listing
+        let mut preview = directory.clone();
+        let _ = unwrap_result!(file_helper.migrate_format(file.clone(), &mut preview, true));
+        let migrated = unwrap_option!(preview.find_file(file.get_name()), "File not found");
+        assert_eq!(migrated.get_metadata().get_schema_version(), CURRENT_SCHEMA_VERSION);
+        assert_eq!(*migrated.get_metadata().get_tags(), vec!["valid".to_string()]);
+        let fetched = unwrap_result!(dir_helper.get(directory.get_key()));
+        assert_eq!(unwrap_option!(fetched.find_file(file.get_name()), "File not found")
+                       .get_metadata()
+                       .get_schema_version(),
+                   0);
+
+        let _ = unwrap_result!(file_helper.migrate_format(file.clone(), &mut directory, false));
+        let fetched = unwrap_result!(dir_helper.get(directory.get_key()));
+        let migrated = unwrap_option!(fetched.find_file(file.get_name()), "File not found");
+        assert_eq!(migrated.get_metadata().get_schema_version(), CURRENT_SCHEMA_VERSION);
+        assert_eq!(*migrated.get_metadata().get_tags(), vec!["valid".to_string()]);
+
+        let mut newer = migrated.clone();
+        newer.get_mut_metadata().set_schema_version(CURRENT_SCHEMA_VERSION + 1);
+        assert!(file_helper.migrate_format(newer, &mut directory, false).is_err());
+    }
+
+    #[test]
+    fn read_chunk() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let mut directory = create_directory(client.clone());
+        let content: Vec<u8> = (0..25).collect();
+        directory = create_file(&file_helper, "hello.txt", &content, directory);
+        let file = unwrap_option!(directory.find_file(&"hello.txt".to_string()),
+                                  "File not found");
+
+        assert_eq!(unwrap_result!(file_helper.read_chunk(file, 0, 10)), content[..10].to_vec());
+        assert_eq!(unwrap_result!(file_helper.read_chunk(file, 1, 10)), content[10..20].to_vec());
+        assert_eq!(unwrap_result!(file_helper.read_chunk(file, 2, 10)), content[20..].to_vec());
+        match file_helper.read_chunk(file, 3, 10) {
+            Err(NfsError::InvalidRangeSpecified) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert!(file_helper.read_chunk(file, 0, 0).is_err());
+    }
+
+    #[test]
+    fn create_encrypted_and_read_and_decrypt() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let mut directory = create_directory(client.clone());
+        let key = [5u8; 32];
+        let content: Vec<u8> = (0..super::ENCRYPTED_CHUNK_SIZE + 10)
+                                   .map(|index| index as u8)
+                                   .collect();
+        directory = unwrap_result!(file_helper.create_encrypted("secret.bin".to_string(),
+                                                                Vec::new(),
+                                                                &content,
+                                                                &key,
+                                                                directory))
+                        .0;
+        directory = create_file(&file_helper, "plain.txt", &[0u8; 10], directory);
+
+        let file = unwrap_option!(directory.find_file(&"secret.bin".to_string()),
+                                  "File not found");
+        assert!(file.get_metadata().is_content_encrypted());
+        assert!(unwrap_result!(file_helper.read(file).read(0, 10)) != content[..10].to_vec());
+        assert_eq!(unwrap_result!(file_helper.read_and_decrypt(file, &key)), content);
+        match file_helper.read_and_decrypt(file, &[6u8; 32]) {
+            Err(NfsError::DecryptionFailed) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        let plain_file = unwrap_option!(directory.find_file(&"plain.txt".to_string()),
+                                        "File not found");
+        assert!(file_helper.read_and_decrypt(plain_file, &key).is_err());
+    }
+
+    #[test]
+    fn temporary_files() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let mut root = create_directory(client.clone());
+        assert_eq!(unwrap_result!(file_helper.gc_temp(&mut root)), 0);
+
+        let (expired_file, _) = unwrap_result!(file_helper.create_temporary(&mut root, 0));
+        let (live_file, temp_directory) =
+            unwrap_result!(file_helper.create_temporary(&mut root, 3600));
+        assert!(expired_file.get_name() != live_file.get_name());
+        assert!(live_file.get_metadata().get_expiry_time().is_some());
+        assert_eq!(temp_directory.get_files().len(), 2);
+        assert_eq!(*temp_directory.get_metadata().get_name(),
+                   ::TEMPORARY_DIRECTORY_NAME.to_string());
+
+        // Files expire only once their expiry time is in the past, so let the clock move on
+        ::std::thread::sleep(::std::time::Duration::from_millis(10));
+        assert_eq!(unwrap_result!(file_helper.gc_temp(&mut root)), 1);
+        let temp_directory = unwrap_result!(dir_helper.get(temp_directory.get_key()));
+        assert!(temp_directory.find_file(expired_file.get_name()).is_none());
+        assert!(temp_directory.find_file(live_file.get_name()).is_some());
+        assert_eq!(unwrap_result!(file_helper.gc_temp(&mut root)), 0);
+    }
+
+    #[test]
+    fn delete_expired() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let mut directory = create_directory(client.clone());
+        assert_eq!(unwrap_result!(file_helper.delete_expired(&mut directory)), 0);
+
+        let expiry_times = [Some(::time::now_utc() - ::time::Duration::hours(1)),
+                            Some(::time::now_utc() + ::time::Duration::hours(1)),
+                            None];
+        for (index, expiry_time) in expiry_times.iter().enumerate() {
+            let name = format!("{}.txt", index);
+            directory = create_file(&file_helper, &name, b"hello", directory);
+            let mut file = unwrap_option!(directory.find_file(&name).map(|file| file.clone()),
+                                          "File not found");
+            match *expiry_time {
+                Some(expiry_time) => file.get_mut_metadata().set_expiry_time(expiry_time),
+                None => file.get_mut_metadata().clear_expiry_time(),
+            }
+            let _ = unwrap_result!(file_helper.update_metadata(file, &mut directory));
+        }
+
+        assert_eq!(unwrap_result!(file_helper.delete_expired(&mut directory)), 1);
+        let directory = unwrap_result!(dir_helper.get(directory.get_key()));
+        assert!(directory.find_file(&"0.txt".to_string()).is_none());
+        assert!(directory.find_file(&"1.txt".to_string()).is_some());
+        assert!(directory.find_file(&"2.txt".to_string()).is_some());
+    }
+
+    #[test]
+    fn writer_write_zeroes() {
+        use helper::writer::ZERO_CHUNK_SIZE;
+
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let directory = create_directory(client.clone());
+        let count = ZERO_CHUNK_SIZE as u64 * 2 + 10;
+        let mut writer = unwrap_result!(file_helper.create("sparse.bin".to_string(),
+                                                           Vec::new(),
+                                                           directory));
+        writer.write(&[1u8; 5], 0);
+        writer.write_zeroes(count, 5);
+        writer.write(&[2u8; 5], count + 5);
+        let (directory, _) = unwrap_result!(writer.close());
+
+        let file = unwrap_option!(directory.find_file(&"sparse.bin".to_string()),
+                                  "File not found");
+        assert_eq!(file.get_size(), count + 10);
+        let mut reader = file_helper.read(file);
+        let content = unwrap_result!(reader.read(0, count + 10));
+        assert_eq!(content[..5].to_vec(), vec![1u8; 5]);
+        assert!(content[5..count as usize + 5].iter().all(|byte| *byte == 0));
+        assert_eq!(content[count as usize + 5..].to_vec(), vec![2u8; 5]);
+    }
+
+    #[test]
+    fn update_content_from_base64() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let mut directory = create_directory(client.clone());
+        directory = create_file(&file_helper, "hello.txt", &[0u8; 10], directory);
+        let find_file = |directory: &DirectoryListing| {
+            unwrap_option!(directory.find_file(&"hello.txt".to_string()), "File not found")
+                .clone()
+        };
+        let read_content = |file: &File| {
+            let mut reader = file_helper.read(file);
+            let size = reader.size();
+            unwrap_result!(reader.read(0, size))
+        };
+
+        let file = find_file(&directory);
+        match file_helper.update_content_from_base64(file.clone(),
+                                                     Mode::Overwrite,
+                                                     "not*base64",
+                                                     directory.clone()) {
+            Err(NfsError::InvalidEncoding(_)) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        // Standard alphabet
+        directory = unwrap_result!(file_helper.update_content_from_base64(file,
+                                                                          Mode::Overwrite,
+                                                                          "+/8=",
+                                                                          directory));
+        let file = find_file(&directory);
+        assert_eq!(read_content(&file), vec![0xfb, 0xff]);
+
+        // URL safe alphabet
+        directory = unwrap_result!(file_helper.update_content_from_base64(file,
+                                                                          Mode::Overwrite,
+                                                                          "-_8=",
+                                                                          directory));
+        assert_eq!(read_content(&find_file(&directory)), vec![0xfb, 0xff]);
+    }
+
+    #[test]
+    fn overwrite_at() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let mut directory = create_directory(client.clone());
+        directory = create_file(&file_helper, "hello.txt", &[0u8; 10], directory);
+        let find_file = |directory: &DirectoryListing| {
+            unwrap_option!(directory.find_file(&"hello.txt".to_string()), "File not found")
+                .clone()
+        };
+
+        let file = find_file(&directory);
+        directory = unwrap_result!(file_helper.overwrite_at(file, 2, &[1u8; 3], directory));
+        let file = find_file(&directory);
+        assert_eq!(file.get_size(), 10);
+        assert_eq!(unwrap_result!(file_helper.read(&file).read(0, 10)),
+                   vec![0u8, 0, 1, 1, 1, 0, 0, 0, 0, 0]);
+
+        // Extends the file
+        directory = unwrap_result!(file_helper.overwrite_at(file, 8, &[2u8; 5], directory));
+        let file = find_file(&directory);
+        assert_eq!(file.get_size(), 13);
+        assert_eq!(unwrap_result!(file_helper.read(&file).read(0, 13)),
+                   vec![0u8, 0, 1, 1, 1, 0, 0, 0, 2, 2, 2, 2, 2]);
+    }
+
+    #[test]
+    fn writer_io_write() {
+        use std::io::{self, Cursor, Write};
+
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let mut directory = create_directory(client.clone());
+        let content: Vec<u8> = (0..100).collect();
+        directory = create_file(&file_helper, "direct.bin", &content, directory);
+
+        let mut writer = unwrap_result!(file_helper.create("copied.bin".to_string(),
+                                                           Vec::new(),
+                                                           directory));
+        assert_eq!(unwrap_result!(io::copy(&mut Cursor::new(content.clone()), &mut writer)),
+                   100);
+        unwrap_result!(writer.write_all(&[1u8, 2]));
+        unwrap_result!(writer.flush());
+        let (directory, _) = unwrap_result!(writer.close());
+
+        let direct = unwrap_option!(directory.find_file(&"direct.bin".to_string()),
+                                    "File not found");
+        let copied = unwrap_option!(directory.find_file(&"copied.bin".to_string()),
+                                    "File not found");
+        assert_eq!(copied.get_size(), 102);
+        let copied_content = unwrap_result!(file_helper.read(copied).read(0, 102));
+        assert_eq!(copied_content[..100].to_vec(),
+                   unwrap_result!(file_helper.read(direct).read(0, 100)));
+        assert_eq!(copied_content[100..].to_vec(), vec![1u8, 2]);
+    }
+
+    #[test]
+    fn find_in_tree() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let mut root = create_directory(client.clone());
+        let (child, _) = unwrap_result!(dir_helper.create("Child".to_string(),
+                                                        ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                        Vec::new(),
+                                                        true,
+                                                        ::AccessLevel::Private,
+                                                        Some(&mut root)));
+        let mut child = create_file(&file_helper, "bar.txt", b"bar", child);
+        let (grandchild, _) = unwrap_result!(dir_helper.create("Grandchild".to_string(),
+                                                             ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                             Vec::new(),
+                                                             true,
+                                                             ::AccessLevel::Private,
+                                                             Some(&mut child)));
+        let _ = create_file(&file_helper, "baz.log", b"baz", grandchild);
+        let root = create_file(&file_helper, "foo.log", b"foo", root);
+
+        let mut matches: Vec<(String, String)> =
+            unwrap_result!(file_helper.find_in_tree(&root, "*.log"))
+                .iter()
+                .map(|&(ref directory, ref file)| {
+                    (directory.get_metadata().get_name().clone(), file.get_name().clone())
+                })
+                .collect();
+        matches.sort();
+        assert_eq!(matches,
+                   vec![("DirName".to_string(), "foo.log".to_string()),
+                        ("Grandchild".to_string(), "baz.log".to_string())]);
+        assert!(unwrap_result!(file_helper.find_in_tree(&root, "*.bin")).is_empty());
+    }
+
+    #[test]
+    fn bulk_create() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let directory = create_directory(client.clone());
+        let files: Vec<(String, Vec<u8>, Vec<u8>)> = (0..5)
+                                                         .map(|index| {
+                                                             (format!("{}.txt", index),
+                                                              vec![index as u8],
+                                                              vec![index as u8; 10 * index])
+                                                         })
+                                                         .collect();
+
+        let (directory, _) = unwrap_result!(file_helper.bulk_create(files.clone(), directory));
+        assert_eq!(directory.get_files().len(), 5);
+        let directory = unwrap_result!(dir_helper.get(directory.get_key()));
+        for (name, user_metadata, content) in files {
+            let file = unwrap_option!(directory.find_file(&name), "File not found");
+            assert_eq!(*file.get_metadata().get_user_metadata(), user_metadata);
+            assert_eq!(file.get_size(), content.len() as u64);
+            let mut reader = file_helper.read(file);
+            assert_eq!(unwrap_result!(reader.read(0, content.len() as u64)), content);
+        }
+
+        let repeated = vec![("new.txt".to_string(), Vec::new(), Vec::new()),
+                            ("new.txt".to_string(), Vec::new(), Vec::new())];
+        match file_helper.bulk_create(repeated, directory.clone()) {
+            Err(NfsError::FileAlreadyExistsWithSameName) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        let existing = vec![("0.txt".to_string(), Vec::new(), Vec::new())];
+        match file_helper.bulk_create(existing, directory) {
+            Err(NfsError::FileAlreadyExistsWithSameName) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn bulk_delete() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let mut directory = create_directory(client.clone());
+        for name in ["a.txt", "b.txt", "c.txt", "d.txt"].iter() {
+            directory = create_file(&file_helper, name, b"hello", directory);
+        }
+
+        let (deleted, _) = unwrap_result!(file_helper.bulk_delete(&["a.txt", "b.txt"],
+                                                                  &mut directory));
+        assert_eq!(deleted, 2);
+        assert_eq!(directory.get_files().len(), 2);
+
+        match file_helper.bulk_delete(&["c.txt", "missing.txt"], &mut directory) {
+            Err(NfsError::PartialFailure { succeeded, errors }) => {
+                assert_eq!(succeeded, 1);
+                assert_eq!(errors.len(), 1);
+                assert_eq!(errors[0].0, "missing.txt".to_string());
+                match errors[0].1 {
+                    NfsError::FileNotFound => (),
+                    ref error => panic!("Unexpected error {:?}", error),
+                }
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+        let directory = unwrap_result!(dir_helper.get(directory.get_key()));
+        assert_eq!(directory.get_files().len(), 1);
+        assert!(directory.find_file(&"d.txt".to_string()).is_some());
+    }
+
+    #[test]
+    fn copy_file() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let mut source = create_directory(client.clone());
+        let (mut destination, _) = unwrap_result!(dir_helper.create("Destination".to_string(),
+                                                                  ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                  Vec::new(),
+                                                                  true,
+                                                                  ::AccessLevel::Private,
+                                                                  None));
+        let file_name = "hello.txt".to_string();
+        source = create_file(&file_helper, &file_name, b"hello world", source);
+        let file = unwrap_option!(source.find_file(&file_name).map(|file| file.clone()),
+                                  "File not found");
+
+        destination = unwrap_result!(file_helper.copy_file(&file, &source, destination)).0;
+        let copy = unwrap_option!(destination.find_file(&file_name), "File not found");
+        assert!(copy.get_id() != file.get_id());
+        assert_eq!(unwrap_result!(file_helper.read(copy).read(0, 11)),
+                   unwrap_result!(file_helper.read(&file).read(0, 11)));
+
+        match file_helper.copy_file(&file, &source, destination.clone()) {
+            Err(NfsError::FileAlreadyExistsWithSameName) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn move_file() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let mut source = create_directory(client.clone());
+        let (mut destination, _) = unwrap_result!(dir_helper.create("Destination".to_string(),
+                                                                  ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                  Vec::new(),
+                                                                  true,
+                                                                  ::AccessLevel::Private,
+                                                                  None));
+        let file_name = "hello.txt".to_string();
+        source = create_file(&file_helper, &file_name, b"hello world", source);
+        let file_id = unwrap_option!(source.find_file(&file_name), "File not found")
+                          .get_id()
+                          .clone();
+
+        match file_helper.move_file("absent.txt", None, &mut source, destination.clone()) {
+            Err(error) => {
+                match *error.root_error() {
+                    NfsError::FileNotFound => (),
+                    _ => panic!("Unexpected error {:?}", error),
+                }
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        destination = unwrap_result!(file_helper.move_file(&file_name,
+                                                           None,
+                                                           &mut source,
+                                                           destination))
+                          .0;
+        assert!(source.find_file(&file_name).is_none());
+        let moved = unwrap_option!(destination.find_file(&file_name), "File not found");
+        assert_eq!(*moved.get_id(), file_id);
+        assert_eq!(unwrap_result!(file_helper.read(moved).read(0, 11)),
+                   b"hello world".to_vec());
+
+        // Both directories are saved
+        let source = unwrap_result!(dir_helper.get(source.get_key()));
+        assert!(source.find_file(&file_name).is_none());
+        let destination = unwrap_result!(dir_helper.get(destination.get_key()));
+        assert!(destination.find_file(&file_name).is_some());
+
+        let mut source = create_file(&file_helper, &file_name, b"hello", source);
+        match file_helper.move_file(&file_name, None, &mut source, destination.clone()) {
+            Err(NfsError::FileAlreadyExistsWithSameName) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        // Moving with a new name
+        let destination = unwrap_result!(file_helper.move_file(&file_name,
+                                                               Some("moved.txt".to_string()),
+                                                               &mut source,
+                                                               destination))
+                              .0;
+        assert!(source.find_file(&file_name).is_none());
+        assert!(destination.find_file(&"moved.txt".to_string()).is_some());
+        assert!(destination.find_file(&file_name).is_some());
+    }
+
+    #[test]
+    fn rename_file_with_move() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let directory = create_directory(client.clone());
+        let directory = create_file(&file_helper, "old.txt", b"hello", directory);
+        let mut directory = create_file(&file_helper, "other.txt", b"other", directory);
+        let file_id = unwrap_option!(directory.find_file(&"old.txt".to_string()),
+                                     "File not found")
+                          .get_id()
+                          .clone();
+
+        let same_directory = directory.clone();
+        match file_helper.move_file("old.txt",
+                                    Some("other.txt".to_string()),
+                                    &mut directory,
+                                    same_directory) {
+            Err(NfsError::FileAlreadyExistsWithSameName) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert!(directory.find_file(&"old.txt".to_string()).is_some());
+
+        let same_directory = directory.clone();
+        let _ = unwrap_result!(file_helper.move_file("old.txt",
+                                                     Some("new.txt".to_string()),
+                                                     &mut directory,
+                                                     same_directory));
+        assert!(directory.find_file(&"old.txt".to_string()).is_none());
+        let directory = unwrap_result!(dir_helper.get(directory.get_key()));
+        assert!(directory.find_file(&"old.txt".to_string()).is_none());
+        let renamed = unwrap_option!(directory.find_file(&"new.txt".to_string()),
+                                     "File not found");
+        assert_eq!(*renamed.get_id(), file_id);
+        assert_eq!(unwrap_result!(file_helper.read(renamed).read(0, 5)), b"hello".to_vec());
+    }
+
+    #[test]
+    fn restore_version() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let mut directory = create_directory(client.clone());
+        let file_name = "hello.txt".to_string();
+        directory = create_file(&file_helper, &file_name, b"original", directory);
+        for content in [&b"first update"[..], &b"second update"[..]].iter() {
+            let file = unwrap_option!(directory.find_file(&file_name).map(|file| file.clone()),
+                                      "File not found");
+            let mut writer = unwrap_result!(file_helper.update_content(file,
+                                                                       Mode::Overwrite,
+                                                                       directory));
+            writer.write(content, 0);
+            directory = unwrap_result!(writer.close()).0;
+        }
+
+        let file = unwrap_option!(directory.find_file(&file_name).map(|file| file.clone()),
+                                  "File not found");
+        let versions = unwrap_result!(file_helper.get_versions(&file, &directory, None));
+        assert_eq!(versions.len(), 3);
+        let _ = unwrap_result!(file_helper.restore_version(&versions[2], &mut directory));
+
+        let restored = unwrap_option!(directory.find_file(&file_name), "File not found");
+        assert_eq!(restored.get_id(), file.get_id());
+        assert_eq!(restored.get_size(), 8);
+        let mut reader = file_helper.read(restored);
+        let size = reader.size();
+        assert_eq!(unwrap_result!(reader.read(0, size)), b"original".to_vec());
+        assert_eq!(restored.get_version(), file.get_version() + 1);
+
+        match file_helper.update_content(file.clone(), Mode::Overwrite, directory.clone()) {
+            Err(NfsError::ConcurrentModification) => (),
+            _ => panic!("Expected ConcurrentModification error"),
+        }
+    }
+
+    #[test]
+    fn update_tag_values() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let mut directory = create_directory(client.clone());
+        let file_name = "hello.txt".to_string();
+        directory = create_file(&file_helper, &file_name, b"hello", directory);
+
+        let mut file = unwrap_option!(directory.find_file(&file_name).map(|file| file.clone()),
+                                      "File not found");
+        file.get_mut_metadata().set_tag_value("author".to_string(), b"maidsafe".to_vec());
+        file.get_mut_metadata().set_tag_value("rating".to_string(), vec![5u8]);
+        let _ = unwrap_result!(file_helper.update_metadata(file, &mut directory));
+
+        let directory = unwrap_result!(dir_helper.get(directory.get_key()));
+        let file = unwrap_option!(directory.find_file(&file_name), "File not found");
+        assert_eq!(file.get_metadata().get_tag_values().len(), 2);
+        assert_eq!(file.get_metadata().get_tag_value("author"), Some(&b"maidsafe"[..]));
+        assert_eq!(file.get_metadata().get_tag_value("rating"), Some(&[5u8][..]));
+    }
+
+    #[test]
+    fn append_content() {
+        use std::io::Write;
+
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let mut directory = create_directory(client.clone());
+        let file_name = "hello.txt".to_string();
+
+        // Appending to an empty file
+        let writer = unwrap_result!(file_helper.create(file_name.clone(), Vec::new(), directory));
+        directory = unwrap_result!(writer.close()).0;
+        let file = unwrap_option!(directory.find_file(&file_name).map(|file| file.clone()),
+                                  "File not found");
+        let mut writer = unwrap_result!(file_helper.update_content(file, Mode::Append, directory));
+        unwrap_result!(writer.write_all(b"hello"));
+        directory = unwrap_result!(writer.close()).0;
+
+        // Appending to existing content
+        let file = unwrap_option!(directory.find_file(&file_name).map(|file| file.clone()),
+                                  "File not found");
+        let mut writer = unwrap_result!(file_helper.update_content(file, Mode::Append, directory));
+        unwrap_result!(writer.write_all(b" "));
+        unwrap_result!(writer.write_all(b"world"));
+        directory = unwrap_result!(writer.close()).0;
+
+        let file = unwrap_option!(directory.find_file(&file_name), "File not found");
+        assert_eq!(file.get_size(), 11);
+        let mut reader = file_helper.read(file);
+        assert_eq!(unwrap_result!(reader.read(0, 11)), b"hello world".to_vec());
+    }
+
+    #[test]
+    fn writer_progress_handler() {
+        use std::sync::mpsc;
+
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let mut directory = create_directory(client.clone());
+        let file_name = "hello.txt".to_string();
+        directory = create_file(&file_helper, &file_name, &[1u8; 100], directory);
+
+        let file = unwrap_option!(directory.find_file(&file_name).map(|file| file.clone()),
+                                  "File not found");
+        let mut writer = unwrap_result!(file_helper.update_content(file, Mode::Append, directory));
+        let (sender, receiver) = mpsc::channel();
+        writer.set_expected_size(160);
+        writer.set_progress_handler(move |written, expected| {
+            unwrap_result!(sender.send((written, expected)));
+        });
+        for _ in 0..3 {
+            unwrap_result!(::std::io::Write::write_all(&mut writer, &[2u8; 20]));
+        }
+        directory = unwrap_result!(writer.close()).0;
+
+        // The handler, and with it the sender, is dropped on close
+        let events: Vec<(u64, u64)> = receiver.iter().collect();
+        assert_eq!(events, vec![(120, 160), (140, 160), (160, 160)]);
+        let file = unwrap_option!(directory.find_file(&file_name), "File not found");
+        assert_eq!(file.get_size(), 160);
+    }
+
+    #[test]
+    fn writer_truncate() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let mut directory = create_directory(client.clone());
+        let file_name = "hello.txt".to_string();
+        directory = create_file(&file_helper, &file_name, &[1u8; 150], directory);
+
+        let file = unwrap_option!(directory.find_file(&file_name).map(|file| file.clone()),
+                                  "File not found");
+        let mut writer = unwrap_result!(file_helper.update_content(file, Mode::Modify, directory));
+        writer.write(&[2u8; 50], 150);
+        unwrap_result!(writer.truncate(100));
+        assert_eq!(writer.total_size(), 100);
+        directory = unwrap_result!(writer.close()).0;
+
+        let file = unwrap_option!(directory.find_file(&file_name), "File not found");
+        assert_eq!(file.get_size(), 100);
+        let mut reader = file_helper.read(file);
+        assert_eq!(reader.size(), 100);
+        assert_eq!(unwrap_result!(reader.read(0, 100)), vec![1u8; 100]);
+    }
+
+    #[test]
+    fn writer_progress() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let mut directory = create_directory(client.clone());
+        let file_name = "hello.txt".to_string();
+        directory = create_file(&file_helper, &file_name, &[1u8; 100], directory);
+
+        let file = unwrap_option!(directory.find_file(&file_name).map(|file| file.clone()),
+                                  "File not found");
+        let mut writer = unwrap_result!(file_helper.update_content(file, Mode::Modify, directory));
+        assert_eq!(writer.bytes_written(), 0);
+        assert_eq!(writer.total_size(), 100);
+
+        let chunk_sizes = [10usize, 25, 40];
+        let mut position = 80;
+        for chunk_size in chunk_sizes.iter() {
+            writer.write(&vec![2u8; *chunk_size], position);
+            position += *chunk_size as u64;
+        }
+        let total = chunk_sizes.iter().fold(0, |sum, size| sum + size);
+        assert_eq!(writer.bytes_written(), total as u64);
+        assert_eq!(writer.total_size(), 155);
+    }
+
+    #[test]
+    fn directory_quota() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let mut directory = create_directory(client.clone());
+        directory.set_quota(Some(1000));
+        let _ = unwrap_result!(dir_helper.update(&directory));
+
+        directory = create_file(&file_helper, "first.bin", &[1u8; 999], directory);
+        assert_eq!(directory.total_size(), 999);
+
+        let mut writer = unwrap_result!(file_helper.create("second.bin".to_string(),
+                                                           Vec::new(),
+                                                           directory.clone()));
+        writer.write(&[2u8; 2], 0);
+        match writer.close() {
+            Err(NfsError::QuotaExceeded { used, limit }) => {
+                assert_eq!(used, 1001);
+                assert_eq!(limit, 1000);
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+        let directory = unwrap_result!(dir_helper.get(directory.get_key()));
+        assert_eq!(directory.get_quota(), Some(1000));
+        assert_eq!(directory.get_files().len(), 1);
+    }
+
+    #[test]
+    fn directory_over_quota_can_shrink() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let directory = create_directory(client.clone());
+        let directory = create_file(&file_helper, "first.bin", &[1u8; 600], directory);
+        let mut directory = create_file(&file_helper, "second.bin", &[2u8; 400], directory);
+
+        // Lowering the quota below the current total size does not grow the directory
+        directory.set_quota(Some(500));
+        let _ = unwrap_result!(dir_helper.update(&directory));
+
+        let mut writer = unwrap_result!(file_helper.create("third.bin".to_string(),
+                                                           Vec::new(),
+                                                           directory.clone()));
+        writer.write(&[3u8; 1], 0);
+        match writer.close() {
+            Err(NfsError::QuotaExceeded { used, limit }) => {
+                assert_eq!(used, 1001);
+                assert_eq!(limit, 500);
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        let _ = unwrap_result!(file_helper.delete("second.bin".to_string(), &mut directory));
+        let directory = unwrap_result!(dir_helper.get(directory.get_key()));
+        assert_eq!(directory.total_size(), 600);
+        assert_eq!(directory.get_files().len(), 1);
+    }
+
+    #[test]
+    fn mime_type() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let directory = create_directory(client.clone());
+        let file_name = "hello.txt".to_string();
+        let mut writer =
+            unwrap_result!(file_helper.create_with_mime_type(file_name.clone(),
+                                                             Vec::new(),
+                                                             Some("text/plain".to_string()),
+                                                             directory));
+        writer.write(b"hello", 0);
+        let (mut directory, _) = unwrap_result!(writer.close());
+
+        let mut file = unwrap_option!(directory.find_file(&file_name).map(|file| file.clone()),
+                                      "File not found");
+        assert_eq!(file.get_metadata().get_mime_type(), Some("text/plain"));
+        file.get_mut_metadata().set_mime_type("text/html".to_string());
+        let _ = unwrap_result!(file_helper.update_metadata(file, &mut directory));
+
+        let directory = unwrap_result!(dir_helper.get(directory.get_key()));
+        let file = unwrap_option!(directory.find_file(&file_name), "File not found");
+        assert_eq!(file.get_metadata().get_mime_type(), Some("text/html"));
+    }
+
+    #[test]
+    fn verify_integrity() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let mut directory = create_directory(client.clone());
+        let file_name = "hello.txt".to_string();
+        directory = create_file(&file_helper, &file_name, b"hello world", directory);
+
+        let mut file = unwrap_option!(directory.find_file(&file_name).map(|file| file.clone()),
+                                      "File not found");
+        assert!(file.get_metadata().get_checksum().is_some());
+        assert!(unwrap_result!(file_helper.read(&file).verify_integrity()));
+
+        file.get_mut_metadata().set_checksum(Some([0u8; 32]));
+        assert!(!unwrap_result!(file_helper.read(&file).verify_integrity()));
+
+        file.get_mut_metadata().set_checksum(None);
+        assert!(unwrap_result!(file_helper.read(&file).verify_integrity()));
+    }
+
+    #[test]
+    fn reader_chunks() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let mut directory = create_directory(client.clone());
+        let content: Vec<u8> = (0..1024 * 1024).map(|index| (index % 251) as u8).collect();
+        directory = create_file(&file_helper, "hello.bin", &content, directory);
+        let file = unwrap_option!(directory.find_file(&"hello.bin".to_string()),
+                                  "File not found");
+
+        let mut reader = file_helper.read(file);
+        let mut read_content = Vec::with_capacity(content.len());
+        let mut chunk_count = 0;
+        for chunk in reader.chunks(64 * 1024) {
+            let chunk = unwrap_result!(chunk);
+            assert_eq!(chunk.len(), 64 * 1024);
+            read_content.extend(chunk);
+            chunk_count += 1;
+        }
+        assert_eq!(chunk_count, 16);
+        assert!(read_content == content);
+
+        let sizes: Vec<usize> = reader.chunks(300 * 1024)
+                                      .map(|chunk| unwrap_result!(chunk).len())
+                                      .collect();
+        assert_eq!(sizes, vec![300 * 1024, 300 * 1024, 300 * 1024, 124 * 1024]);
+    }
+
+    #[test]
+    fn reader_io_read_and_seek() {
+        use std::io::{BufReader, Read, Seek, SeekFrom};
+
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let mut directory = create_directory(client.clone());
+        let content: Vec<u8> = (0..100).collect();
+        directory = create_file(&file_helper, "hello.bin", &content, directory);
+        let file = unwrap_option!(directory.find_file(&"hello.bin".to_string()),
+                                  "File not found");
+
+        let mut read_content = Vec::new();
+        let _ = unwrap_result!(BufReader::new(file_helper.read(file))
+                                   .read_to_end(&mut read_content));
+        assert_eq!(read_content, unwrap_result!(file_helper.read(file).read(0, 100)));
+
+        let mut reader = file_helper.read(file);
+        let mut buffer = [0u8; 5];
+        assert_eq!(unwrap_result!(reader.seek(SeekFrom::Start(50))), 50);
+        unwrap_result!(reader.read_exact(&mut buffer));
+        assert_eq!(buffer, [50u8, 51, 52, 53, 54]);
+        assert_eq!(unwrap_result!(reader.seek(SeekFrom::Current(-20))), 35);
+        unwrap_result!(reader.read_exact(&mut buffer));
+        assert_eq!(buffer, [35u8, 36, 37, 38, 39]);
+        assert_eq!(unwrap_result!(reader.seek(SeekFrom::End(-2))), 98);
+        assert_eq!(unwrap_result!(Read::read(&mut reader, &mut buffer)), 2);
+        assert_eq!(buffer[..2], [98u8, 99]);
+        assert_eq!(unwrap_result!(Read::read(&mut reader, &mut buffer)), 0);
+        assert!(reader.seek(SeekFrom::Current(-200)).is_err());
+    }
+
+    #[test]
+    fn typed_user_metadata() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let mut directory = create_directory(client.clone());
+        directory = create_file(&file_helper, "hello.txt", &[0u8; 10], directory);
+        let file = unwrap_option!(directory.find_file(&"hello.txt".to_string())
+                                           .map(|file| file.clone()),
+                                  "File not found");
+        let metadata = ("text/plain".to_string(), 10u64);
+        let _ = unwrap_result!(file_helper.update_user_metadata_cbor(file,
+                                                                     metadata.clone(),
+                                                                     &mut directory));
+        let file = unwrap_option!(directory.find_file(&"hello.txt".to_string()),
+                                  "File not found");
+        let retrieved: (String, u64) = unwrap_result!(FileHelper::get_user_metadata_cbor(file));
+        assert_eq!(retrieved, metadata);
+        assert!(FileHelper::get_user_metadata_cbor::<Vec<String>>(file).is_err());
+    }
+
+    #[test]
+    fn history_summary() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (directory, _) = unwrap_result!(dir_helper.create("DirName".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        let file_helper = FileHelper::new(client.clone());
+        let file_name = "hello.txt".to_string();
+        let mut writer = unwrap_result!(file_helper.create(file_name.clone(),
+                                                           Vec::new(),
+                                                           directory));
+        writer.write(&vec![0u8; 100], 0);
+        let (directory, _) = unwrap_result!(writer.close());
+        let file = unwrap_option!(directory.find_file(&file_name).map(|file| file.clone()),
+                                  "File not found");
+        let mut writer = unwrap_result!(file_helper.update_content(file,
+                                                                   Mode::Overwrite,
+                                                                   directory));
+        writer.write(&vec![1u8; 50], 0);
+        let (directory, _) = unwrap_result!(writer.close());
+
+        let file = unwrap_option!(directory.find_file(&file_name), "File not found");
+        let summary = unwrap_result!(file_helper.get_history_summary(file, &directory));
+        assert_eq!(summary.version_count, 2);
+        assert_eq!(summary.min_size, 50);
+        assert_eq!(summary.max_size, 100);
+        assert_eq!(summary.current_size, 50);
+        assert_eq!(summary.first_created, *file.get_metadata().get_created_time());
+        assert_eq!(summary.last_modified, *file.get_modified_time());
+    }
+
+    #[test]
+    fn versions_in_range() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let mut directory = create_directory(client.clone());
+        directory = create_file(&file_helper, "hello.txt", &[0u8; 10], directory);
+        for content in &[[1u8; 10], [2u8; 10]] {
+            let file = unwrap_option!(directory.find_file(&"hello.txt".to_string()),
+                                      "File not found")
+                           .clone();
+            let mut writer = unwrap_result!(file_helper.update_content(file,
+                                                                       Mode::Overwrite,
+                                                                       directory));
+            writer.write(content, 0);
+            directory = unwrap_result!(writer.close()).0;
+        }
+        let file = unwrap_option!(directory.find_file(&"hello.txt".to_string()),
+                                  "File not found");
+        let mut versions = unwrap_result!(file_helper.get_versions(file, &directory, None));
+        assert_eq!(versions.len(), 3);
+        versions.reverse();
+        let modified_time = |index: usize| *versions[index].get_modified_time();
+
+        let in_range = unwrap_result!(file_helper.get_versions_in_range(file,
+                                                                        &directory,
+                                                                        modified_time(1),
+                                                                        modified_time(2)));
+        assert_eq!(in_range, versions[1..].to_vec());
+        let in_range = unwrap_result!(file_helper.get_versions_in_range(file,
+                                                                        &directory,
+                                                                        modified_time(0),
+                                                                        modified_time(0)));
+        assert_eq!(in_range, vec![versions[0].clone()]);
+
+        let start = modified_time(2) + ::time::Duration::days(1);
+        let end = start + ::time::Duration::days(1);
+        assert!(unwrap_result!(file_helper.get_versions_in_range(file, &directory, start, end))
+                    .is_empty());
+        assert!(file_helper.get_versions_in_range(file, &directory, end, start).is_err());
+    }
+
+    #[cfg(feature = "json")]
+    #[derive(RustcEncodable, RustcDecodable, PartialEq, Debug)]
+    struct Settings {
+        name: String,
+        values: Vec<u64>,
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn write_and_read_json() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (directory, _) = unwrap_result!(dir_helper.create("DirName".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        let file_helper = FileHelper::new(client.clone());
+        let file_name = "settings.json".to_string();
+        let settings = Settings {
+            name: "Home".to_string(),
+            values: vec![1, 2, 3],
+        };
+        let mut writer = unwrap_result!(file_helper.create(file_name.clone(),
+                                                           Vec::new(),
+                                                           directory));
+        unwrap_result!(writer.write_json(&settings));
+        let (directory, _) = unwrap_result!(writer.close());
+        let file = unwrap_option!(directory.find_file(&file_name), "File not found");
+        let mut reader = file_helper.read(file);
+        assert_eq!(unwrap_result!(reader.read_json::<Settings>()), settings);
+        assert!(reader.read_json::<Vec<u8>>().is_err());
+    }
+
+    #[test]
+    fn encrypt_and_decrypt_datamap_for_recipient() {
+        let sender = get_client();
+        let recipient = get_client();
+        let sender_public_key =
+            unwrap_result!(unwrap_result!(sender.lock()).get_public_encryption_key()).0;
+        let recipient_public_key =
+            unwrap_result!(unwrap_result!(recipient.lock()).get_public_encryption_key()).0;
+
+        let dir_helper = DirectoryHelper::new(sender.clone());
+        let (directory, _) = unwrap_result!(dir_helper.create("DirName".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        let file_helper = FileHelper::new(sender.clone());
+        let file_name = "hello.txt".to_string();
+        let mut writer = unwrap_result!(file_helper.create(file_name.clone(),
+                                                           Vec::new(),
+                                                           directory));
+        writer.write(&vec![0u8; 100], 0);
+        let (directory, _) = unwrap_result!(writer.close());
+        let file = unwrap_option!(directory.find_file(&file_name).map(|file| file.clone()),
+                                  "File not found");
+        let datamap = file.get_datamap().clone();
+        let version = file.get_version();
+
+        let directory = unwrap_result!(file_helper.encrypt_for_recipient(file,
+                                                                         &recipient_public_key,
+                                                                         directory));
+        let file = unwrap_option!(directory.find_file(&file_name), "File not found");
+        assert!(file.get_metadata().is_recipient_encrypted());
+        assert_eq!(file.get_datamap().len(), 0);
+        assert_eq!(file.get_size(), 0);
+        assert_eq!(file.get_metadata().get_checksum(), None);
+        assert_eq!(file.get_version(), version + 1);
+
+        let recipient_file_helper = FileHelper::new(recipient.clone());
+        assert_eq!(unwrap_result!(recipient_file_helper.decrypt_datamap(file, &sender_public_key)),
+                   datamap);
+        assert!(file_helper.decrypt_datamap(file, &sender_public_key).is_err());
+
+        // Encrypting again would lose the encrypted data-map
+        match file_helper.encrypt_for_recipient(file.clone(),
+                                                &recipient_public_key,
+                                                directory.clone()) {
+            Err(NfsError::ParameterIsNotValid) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn encrypt_for_recipient_rejects_user_metadata() {
+        let client = get_client();
+        let recipient_public_key =
+            unwrap_result!(unwrap_result!(get_client().lock()).get_public_encryption_key()).0;
+        let file_helper = FileHelper::new(client.clone());
+        let directory = create_directory(client.clone());
+        let mut writer = unwrap_result!(file_helper.create("hello.txt".to_string(),
+                                                           vec![1u8; 10],
+                                                           directory));
+        writer.write(&[0u8; 10], 0);
+        let (directory, _) = unwrap_result!(writer.close());
+        let file = unwrap_option!(directory.find_file(&"hello.txt".to_string()),
+                                  "File not found")
+                       .clone();
+        match file_helper.encrypt_for_recipient(file, &recipient_public_key, directory) {
+            Err(NfsError::ParameterIsNotValid) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn detect_identical_content() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let directory = create_directory(client.clone());
+        let directory = create_file(&file_helper, "first.txt", &[1u8; 5000], directory);
+        let directory = create_file(&file_helper, "second.txt", &[1u8; 5000], directory);
+        let directory = create_file(&file_helper, "third.txt", &[2u8; 5000], directory);
+        let directory = create_file(&file_helper, "fourth.txt", &[3u8; 10], directory);
+        let directory = create_file(&file_helper, "fifth.txt", &[3u8; 10], directory);
+
+        let find = |name: &str| {
+            unwrap_option!(directory.find_file(&name.to_string()), "File not found")
+        };
+        assert!(FileHelper::are_content_identical(find("first.txt"), find("second.txt")));
+        assert!(!FileHelper::are_content_identical(find("first.txt"), find("third.txt")));
+        assert!(FileHelper::are_content_identical(find("fourth.txt"), find("fifth.txt")));
+
+        let duplicates = file_helper.find_duplicates(&directory);
+        assert_eq!(duplicates.len(), 2);
+        let mut names: Vec<Vec<String>> =
+            duplicates.iter()
+                      .map(|group| {
+                          let mut names: Vec<String> =
+                              group.iter().map(|file| file.get_name().clone()).collect();
+                          names.sort();
+                          names
+                      })
+                      .collect();
+        names.sort();
+        assert_eq!(names,
+                   vec![vec!["fifth.txt".to_string(), "fourth.txt".to_string()],
+                        vec!["first.txt".to_string(), "second.txt".to_string()]]);
+    }
+
+    #[test]
+    fn version_history() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let directory = create_directory(client.clone());
+        let directory = create_file(&file_helper, "file.txt", &[0u8], directory);
+        let mut directory = directory;
+        for content in 1u8..3 {
+            let file = unwrap_option!(directory.find_file(&"file.txt".to_string()),
+                                      "File not found")
+                           .clone();
+            let mut writer = unwrap_result!(file_helper.update_content(file,
+                                                                       Mode::Overwrite,
+                                                                       directory));
+            writer.write(&[content], 0);
+            directory = unwrap_result!(writer.close()).0;
+        }
+        let file = unwrap_option!(directory.find_file(&"file.txt".to_string()), "File not found")
+                       .clone();
+
+        let history = unwrap_result!(file_helper.get_version_history(&file, &directory, None));
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.iter().map(|version| version.file.clone()).collect::<Vec<_>>(),
+                   unwrap_result!(file_helper.get_versions(&file, &directory, None)));
+        let oldest_first: Vec<&FileVersion> = history.iter().rev().collect();
+        for pair in oldest_first.windows(2) {
+            assert!(pair[0].directory_modified_time < pair[1].directory_modified_time);
+            assert!(pair[0].version_index < pair[1].version_index);
+        }
+
+        let latest = unwrap_result!(file_helper.get_version_history(&file, &directory, Some(1)));
+        assert_eq!(latest.len(), 1);
+        assert_eq!(latest[0].version_index, history[0].version_index);
+
+        // Versions of the directory which only change other files do not introduce a version
+        let introduced_by = history[0].version_index;
+        let directory = create_file(&file_helper, "other.txt", &[0u8], directory);
+        let history = unwrap_result!(file_helper.get_version_history(&file, &directory, None));
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0].version_index, introduced_by);
+        let latest = unwrap_result!(file_helper.get_version_history(&file, &directory, Some(1)));
+        assert_eq!(latest[0].version_index, introduced_by);
+    }
+
+    #[test]
+    fn prune_file_versions() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let directory = create_directory(client.clone());
+        let mut directory = create_file(&file_helper, "file.txt", &[0u8], directory);
+        for content in 1u8..5 {
+            let file = unwrap_option!(directory.find_file(&"file.txt".to_string()),
+                                      "File not found")
+                           .clone();
+            let mut writer = unwrap_result!(file_helper.update_content(file,
+                                                                       Mode::Overwrite,
+                                                                       directory));
+            writer.write(&[content], 0);
+            directory = unwrap_result!(writer.close()).0;
+        }
+        let file = unwrap_option!(directory.find_file(&"file.txt".to_string()), "File not found");
+        assert_eq!(unwrap_result!(file_helper.get_versions(file, &directory, None)).len(), 5);
+
+        assert!(file_helper.prune_versions(file, &directory, 0).is_err());
+        assert_eq!(unwrap_result!(file_helper.prune_versions(file, &directory, 5)), 0);
+        assert_eq!(unwrap_result!(file_helper.prune_versions(file, &directory, 2)), 3);
+        let versions = unwrap_result!(file_helper.get_versions(file, &directory, None));
+        assert_eq!(versions.len(), 2);
+        assert_eq!(unwrap_result!(file_helper.read(&versions[0]).read(0, 1)), vec![4u8]);
+        assert_eq!(unwrap_result!(file_helper.read(&versions[1]).read(0, 1)), vec![3u8]);
+
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let latest = unwrap_result!(dir_helper.get(directory.get_key()));
+        assert!(latest.find_file(&"file.txt".to_string()).is_some());
+    }
+
+    #[test]
+    fn write_lock() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let directory = create_directory(client.clone());
+        let mut directory = create_file(&file_helper, "file.txt", &[0u8], directory);
+
+        let lock = unwrap_result!(file_helper.lock("file.txt", &mut directory));
+        match file_helper.lock("file.txt", &mut directory) {
+            Err(NfsError::FileLocked) => (),
+            _ => panic!("Expected FileLocked error"),
+        }
+        let file = unwrap_option!(directory.find_file(&"file.txt".to_string()), "File not found")
+                       .clone();
+        match file_helper.update_content(file.clone(), Mode::Overwrite, directory.clone()) {
+            Err(NfsError::FileLocked) => (),
+            _ => panic!("Expected FileLocked error"),
+        }
+        let mut writer = unwrap_result!(file_helper.update_content_with_lock(file,
+                                                                             Mode::Overwrite,
+                                                                             directory,
+                                                                             &lock));
+        writer.write(&[1u8], 0);
+        let (mut directory, _) = unwrap_result!(writer.close());
+
+        drop(lock);
+        let lock = unwrap_result!(file_helper.lock("file.txt", &mut directory));
+        let file = unwrap_option!(directory.find_file(&"file.txt".to_string()), "File not found");
+        assert_eq!(file.get_metadata().get_lock_token(), Some(lock.get_token()));
+        assert_eq!(unwrap_result!(file_helper.read(file).read(0, 1)), vec![1u8]);
+    }
+
+    #[test]
+    fn delete_missing_file_reports_name() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let mut directory = create_directory(client.clone());
+        match file_helper.delete("missing.txt".to_string(), &mut directory) {
+            Err(error) => {
+                assert!(format!("{}", error).contains("missing.txt"));
+                match *error.root_error() {
+                    NfsError::FileNotFound => (),
+                    _ => panic!("Unexpected error {:?}", error),
+                }
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn concurrent_modification() {
+        let client = get_client();
+        let first_session = FileHelper::new(client.clone());
+        let second_session = FileHelper::new(client.clone());
+        let directory = create_directory(client.clone());
+        let directory = create_file(&first_session, "file.txt", &[0u8], directory);
+        let file = unwrap_option!(directory.find_file(&"file.txt".to_string()), "File not found")
+                       .clone();
+        let stale_directory = directory.clone();
+        let stale_file = file.clone();
+
+        let mut writer = unwrap_result!(first_session.update_content(file,
+                                                                     Mode::Overwrite,
+                                                                     directory));
+        writer.write(&[1u8], 0);
+        let (directory, _) = unwrap_result!(writer.close());
+        let file = unwrap_option!(directory.find_file(&"file.txt".to_string()), "File not found");
+        assert_eq!(file.get_version(), stale_file.get_version() + 1);
+
+        match second_session.update_content_and_metadata(stale_file.clone(),
+                                                         Mode::Overwrite,
+                                                         stale_file.get_metadata().clone(),
+                                                         stale_directory.clone()) {
+            Err(NfsError::ConcurrentModification) => (),
+            _ => panic!("Expected ConcurrentModification error"),
+        }
+        match second_session.update_content(stale_file, Mode::Overwrite, stale_directory) {
+            Err(NfsError::ConcurrentModification) => (),
+            _ => panic!("Expected ConcurrentModification error"),
+        }
+        assert!(second_session.update_content(file.clone(), Mode::Overwrite, directory.clone())
+                              .is_ok());
+    }
+
+    #[test]
+    fn write_lock_checked_on_latest_directory() {
+        let client = get_client();
+        let first_session = FileHelper::new(client.clone());
+        let second_session = FileHelper::new(client.clone());
+        let directory = create_directory(client.clone());
+        let mut directory = create_file(&first_session, "file.txt", &[0u8], directory);
+        let mut stale_directory = directory.clone();
+        let file = unwrap_option!(stale_directory.find_file(&"file.txt".to_string()),
+                                  "File not found")
+                       .clone();
+
+        let _lock = unwrap_result!(first_session.lock("file.txt", &mut directory));
+
+        match second_session.update_content(file.clone(),
+                                            Mode::Overwrite,
+                                            stale_directory.clone()) {
+            Err(NfsError::FileLocked) => (),
+            _ => panic!("Expected FileLocked error"),
+        }
+        match second_session.update_content_and_metadata(file.clone(),
+                                                         Mode::Overwrite,
+                                                         file.get_metadata().clone(),
+                                                         stale_directory.clone()) {
+            Err(NfsError::FileLocked) => (),
+            _ => panic!("Expected FileLocked error"),
+        }
+        match second_session.update_metadata(file.clone(), &mut stale_directory) {
+            Err(NfsError::FileLocked) => (),
+            _ => panic!("Expected FileLocked error"),
+        }
+        match second_session.restore_version(&file, &mut stale_directory) {
+            Err(NfsError::FileLocked) => (),
+            _ => panic!("Expected FileLocked error"),
+        }
+    }
+
+    #[test]
+    fn large_file() {
+        const SIZE: usize = 5 * 1024 * 1024 + 3;
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let directory = create_directory(client.clone());
+        let name = generate_random_file_name();
+        let (directory, file) = fill_file(&file_helper, &directory, &name, SIZE);
+        assert_eq!(file.get_size(), SIZE as u64);
+        assert!(directory.find_file(&name).is_some());
+
+        let mut reader = file_helper.read(&file);
+        let content = unwrap_result!(reader.read(0, SIZE as u64));
+        assert_eq!(content.len(), SIZE);
+        let tail = unwrap_result!(reader.read(SIZE as u64 - 10, 10));
+        assert_eq!(&tail[..], &content[SIZE - 10..]);
+    }
+
+    #[test]
+    fn close_and_purge() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let directory = create_unversioned_directory(client.clone());
+        let (directory, file) = fill_file(&file_helper, &directory, "file.bin", 10 * 1024);
+        match *file.get_datamap() {
+            DataMap::Chunks(_) => (),
+            _ => panic!("Expected the content to be held in chunks"),
+        }
+
+        let mut writer = unwrap_result!(file_helper.update_content(file,
+                                                                   Mode::Overwrite,
+                                                                   directory));
+        writer.write(&[7u8; 8 * 1024], 0);
+        let (directory, _) = unwrap_result!(writer.close_and_purge());
+        let file = unwrap_option!(directory.find_file(&"file.bin".to_string()), "File not found");
+        assert_eq!(unwrap_result!(file_helper.read(file).read(0, 8 * 1024)),
+                   vec![7u8; 8 * 1024]);
+
+        let stored_directory = unwrap_result!(dir_helper.get(directory.get_key()));
+        let stored_file = unwrap_option!(stored_directory.find_file(&"file.bin".to_string()),
+                                         "File not found");
+        assert_eq!(stored_file.get_datamap(), file.get_datamap());
+        assert_eq!(unwrap_result!(file_helper.read(stored_file).read(0, 8 * 1024)),
+                   vec![7u8; 8 * 1024]);
+    }
+
+    #[test]
+    fn close_and_purge_failure_keeps_replaced_chunks() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let directory = create_unversioned_directory(client.clone());
+        let (mut directory, file) = fill_file(&file_helper, &directory, "file.bin", 10 * 1024);
+        let content = unwrap_result!(file_helper.read(&file).read(0, 10 * 1024));
+
+        directory.set_quota(Some(10 * 1024));
+        let mut writer = unwrap_result!(file_helper.update_content(file.clone(),
+                                                                   Mode::Overwrite,
+                                                                   directory));
+        writer.write(&[7u8; 20 * 1024], 0);
+        match writer.close_and_purge() {
+            Err(NfsError::QuotaExceeded { .. }) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert_eq!(unwrap_result!(file_helper.read(&file).read(0, 10 * 1024)), content);
+    }
+
+    #[test]
+    fn close_and_purge_rejects_versioned_parent() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let directory = create_directory(client.clone());
+        let (directory, file) = fill_file(&file_helper, &directory, "file.bin", 10 * 1024);
+        let content = unwrap_result!(file_helper.read(&file).read(0, 10 * 1024));
+
+        let mut writer = unwrap_result!(file_helper.update_content(file.clone(),
+                                                                   Mode::Overwrite,
+                                                                   directory));
+        writer.write(&[7u8; 8 * 1024], 0);
+        match writer.close_and_purge() {
+            Err(NfsError::ParameterIsNotValid) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert_eq!(unwrap_result!(file_helper.read(&file).read(0, 10 * 1024)), content);
+    }
+
+    #[test]
+    fn force_unlock() {
+        let client = get_client();
+        let file_helper = FileHelper::new(client.clone());
+        let directory = create_directory(client.clone());
+        let mut directory = create_file(&file_helper, "file.txt", &[0u8], directory);
+
+        // A lock which is never released, as if the session holding it had died
+        let stale_lock = unwrap_result!(file_helper.lock("file.txt", &mut directory));
+        ::std::mem::forget(stale_lock);
+        match file_helper.lock("file.txt", &mut directory) {
+            Err(NfsError::FileLocked) => (),
+            _ => panic!("Expected FileLocked error"),
+        }
+
+        let _ = unwrap_result!(file_helper.force_unlock("file.txt", &mut directory));
+        {
+            let file = unwrap_option!(directory.find_file(&"file.txt".to_string()),
+                                      "File not found");
+            assert_eq!(file.get_metadata().get_lock_token(), None);
+        }
+        let _ = unwrap_result!(file_helper.lock("file.txt", &mut directory));
+    }
 }
@@ -19,6 +19,8 @@
 pub mod writer;
 /// Data from a file can be read using Reader
 pub mod reader;
+/// WriteLock guards a file against concurrent updates of its content
+pub mod write_lock;
 /// FileHelper provides functions for CRUD on file
 pub mod file_helper;
 /// DirectoryHelper provides functions for CRUD on DirectoryListing
@@ -15,35 +15,65 @@
 // Please review the Licences for the specific language governing permissions and limitations
 // relating to use of the SAFE Network Software.
 
+use std::cmp;
+use std::io::{self, Read, Seek, SeekFrom};
 use std::sync::{Arc, Mutex};
 
 use errors::NfsError;
 use file::File;
+#[cfg(feature = "json")]
+use rustc_serialize::Decodable;
+#[cfg(feature = "json")]
+use rustc_serialize::json;
 use safe_core::client::Client;
 use safe_core::SelfEncryptionStorage;
-use self_encryption::SelfEncryptor;
+use self_encryption::{SelfEncryptor, Storage};
+use utils;
 
 /// Reader is used to read contents of a File. It can read in chunks if the file happens to be very
 /// large
 #[allow(dead_code)]
-pub struct Reader<'a> {
+pub struct Reader<'a, S: Storage = SelfEncryptionStorage> {
     client: Arc<Mutex<Client>>,
-    self_encryptor: SelfEncryptor<SelfEncryptionStorage>,
+    self_encryptor: SelfEncryptor<S>,
     file: &'a File,
+    position: u64,
+    buffer_size: u64,
+    buffer: Vec<u8>,
+    buffer_offset: u64,
 }
 
 impl<'a> Reader<'a> {
     /// Create a new instance of Reader
     pub fn new(client: Arc<Mutex<Client>>, file: &'a File) -> Reader {
         let se_storage = SelfEncryptionStorage::new(client.clone());
+        Reader::with_storage(client, file, se_storage)
+    }
+}
 
+impl<'a, S: Storage> Reader<'a, S> {
+    /// Create a new instance of Reader fetching the chunks of the file from `storage`
+    fn with_storage(client: Arc<Mutex<Client>>, file: &'a File, storage: Arc<S>) -> Reader<'a, S> {
         Reader {
-            client: client.clone(),
-            self_encryptor: SelfEncryptor::new(se_storage, file.get_datamap().clone()),
+            client: client,
+            self_encryptor: SelfEncryptor::new(storage, file.get_datamap().clone()),
             file: file,
+            position: 0,
+            buffer_size: 0,
+            buffer: Vec::new(),
+            buffer_offset: 0,
         }
     }
 
+    /// Sets the size of the read-ahead buffer. Reads shorter than `size` bytes fetch `size` bytes
+    /// from the network and serve subsequent reads within the fetched range from the buffer.
+    /// A size of 0, which is the default, disables read-ahead
+    pub fn set_buffer_size(&mut self, size: u64) {
+        self.buffer_size = size;
+        self.buffer = Vec::new();
+        self.buffer_offset = 0;
+    }
+
     /// Returns the total size of the file/blob
     pub fn size(&self) -> u64 {
         debug!("Retrieving file length ...");
@@ -51,6 +81,7 @@ impl<'a> Reader<'a> {
     }
 
     /// Read data from file/blob
+    /// This does not move the position used by `std::io::Read` and `std::io::Seek`
     pub fn read(&mut self, position: u64, length: u64) -> Result<Vec<u8>, NfsError> {
         if (position + length) > self.size() {
             Err(NfsError::InvalidRangeSpecified)
@@ -58,7 +89,194 @@ impl<'a> Reader<'a> {
             debug!("Reading {len} bytes of data from file starting at offset of {pos} bytes ...",
                    len = length,
                    pos = position);
-            Ok(self.self_encryptor.read(position, length))
+            Ok(self.fetch(position, length))
+        }
+    }
+
+    /// Reads the complete content of the file/blob and decodes it from JSON
+    #[cfg(feature = "json")]
+    pub fn read_json<T: Decodable>(&mut self) -> Result<T, NfsError> {
+        let size = self.size();
+        let content = try!(self.read(0, size));
+        let json = try!(String::from_utf8(content).map_err(|_| {
+            NfsError::InvalidEncoding("File content is not valid UTF-8".to_string())
+        }));
+        debug!("Decoding file content from JSON ...");
+        Ok(try!(json::decode(&json)))
+    }
+
+    /// Reads the complete content of the file/blob, in slices of `utils::HASH_SLICE_SIZE` bytes,
+    /// and compares its SHA3-256 hash with the checksum recorded in the metadata of the file.
+    /// Returns true if they match or if no checksum was recorded
+    pub fn verify_integrity(&mut self) -> Result<bool, NfsError> {
+        let checksum = match self.file.get_metadata().get_checksum() {
+            Some(checksum) => *checksum,
+            None => return Ok(true),
+        };
+        let size = self.size();
+        debug!("Verifying hash of file content ...");
+        let hash = try!(utils::sha3_256_by_slices(size, |position, length| {
+            self.read(position, length)
+        }));
+        Ok(hash == checksum)
+    }
+
+    /// Returns an iterator over the content of the file/blob in chunks of `chunk_size` bytes. The
+    /// last chunk may be smaller. Each chunk is read only when the iterator is advanced.
+    /// Panics if `chunk_size` is 0
+    pub fn chunks<'r>(&'r mut self, chunk_size: u64) -> ReaderChunks<'r, 'a, S> {
+        assert!(chunk_size > 0, "Invalid chunk size - chunk size is 0");
+        ReaderChunks {
+            reader: self,
+            chunk_size: chunk_size,
+            offset: 0,
+        }
+    }
+
+    /// Reads `length` bytes starting at `position`, which must lie within the file/blob, through
+    /// the read-ahead buffer
+    fn fetch(&mut self, position: u64, length: u64) -> Vec<u8> {
+        let buffer_end = self.buffer_offset + self.buffer.len() as u64;
+        if position >= self.buffer_offset && position + length <= buffer_end {
+            let start = (position - self.buffer_offset) as usize;
+            return self.buffer[start..start + length as usize].to_vec();
+        }
+        if length >= self.buffer_size {
+            return self.self_encryptor.read(position, length);
+        }
+        let fetch_length = cmp::min(self.buffer_size, self.size() - position);
+        debug!("Filling read-ahead buffer with {:?} bytes ...", fetch_length);
+        self.buffer = self.self_encryptor.read(position, fetch_length);
+        self.buffer_offset = position;
+        self.buffer[..length as usize].to_vec()
+    }
+}
+
+/// Iterator over the content of a file/blob in chunks, returned by `Reader::chunks`
+pub struct ReaderChunks<'r, 'a: 'r, S: Storage + 'r = SelfEncryptionStorage> {
+    reader: &'r mut Reader<'a, S>,
+    chunk_size: u64,
+    offset: u64,
+}
+
+impl<'r, 'a, S: Storage> Iterator for ReaderChunks<'r, 'a, S> {
+    type Item = Result<Vec<u8>, NfsError>;
+
+    fn next(&mut self) -> Option<Result<Vec<u8>, NfsError>> {
+        let size = self.reader.size();
+        if self.offset >= size {
+            return None;
+        }
+        let length = cmp::min(self.chunk_size, size - self.offset);
+        let chunk = self.reader.read(self.offset, length);
+        self.offset += length;
+        Some(chunk)
+    }
+}
+
+impl<'a, S: Storage> Read for Reader<'a, S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let size = self.size();
+        if self.position >= size {
+            return Ok(0);
+        }
+        let length = cmp::min(buf.len() as u64, size - self.position);
+        let position = self.position;
+        let data = self.fetch(position, length);
+        for (destination, source) in buf.iter_mut().zip(data.iter()) {
+            *destination = *source;
+        }
+        self.position += data.len() as u64;
+        Ok(data.len())
+    }
+}
+
+impl<'a, S: Storage> Seek for Reader<'a, S> {
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        let new_position = match position {
+            SeekFrom::Start(offset) => offset as i64,
+            SeekFrom::End(offset) => self.size() as i64 + offset,
+            SeekFrom::Current(offset) => self.position as i64 + offset,
+        };
+        if new_position < 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "Invalid seek to a negative position"));
+        }
+        self.position = new_position as u64;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use helper::directory_helper::DirectoryHelper;
+    use helper::file_helper::FileHelper;
+    use helper::reader::Reader;
+    use safe_core::SelfEncryptionStorage;
+    use safe_core::utility::test_utils;
+    use self_encryption::{DataMap, Storage};
+
+    // Counts the chunks fetched from the network
+    struct CountingStorage {
+        storage: Arc<SelfEncryptionStorage>,
+        get_count: AtomicUsize,
+    }
+
+    impl Storage for CountingStorage {
+        fn get(&self, name: &[u8]) -> Vec<u8> {
+            let _ = self.get_count.fetch_add(1, Ordering::SeqCst);
+            self.storage.get(name)
+        }
+
+        fn put(&self, name: Vec<u8>, data: Vec<u8>) {
+            self.storage.put(name, data)
+        }
+    }
+
+    #[test]
+    fn read_ahead_buffer() {
+        const READ_SIZE: u64 = 64 * 1024;
+        let test_client = unwrap_result!(test_utils::get_client());
+        let client = Arc::new(Mutex::new(test_client));
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let file_helper = FileHelper::new(client.clone());
+        let (directory, _) = unwrap_result!(dir_helper.create("DirName".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        let content: Vec<u8> = (0..10 * READ_SIZE).map(|index| (index % 251) as u8).collect();
+        let mut writer = unwrap_result!(file_helper.create("file.bin".to_string(),
+                                                           Vec::new(),
+                                                           directory));
+        writer.write(&content, 0);
+        let (directory, _) = unwrap_result!(writer.close());
+        let file = unwrap_option!(directory.find_file(&"file.bin".to_string()), "File not found");
+
+        let chunk_count = match *file.get_datamap() {
+            DataMap::Chunks(ref chunks) => chunks.len(),
+            _ => panic!("Expected the content to be held in chunks"),
+        };
+
+        let storage = Arc::new(CountingStorage {
+            storage: SelfEncryptionStorage::new(client.clone()),
+            get_count: AtomicUsize::new(0),
+        });
+        let mut reader = Reader::with_storage(client.clone(), file, storage.clone());
+        reader.set_buffer_size(1024 * 1024);
+        for index in 0..10 {
+            let offset = index * READ_SIZE;
+            assert_eq!(unwrap_result!(reader.read(offset, READ_SIZE)),
+                       &content[offset as usize..(offset + READ_SIZE) as usize]);
+        }
+        // Each chunk is fetched from the network only once
+        assert_eq!(storage.get_count.load(Ordering::SeqCst), chunk_count);
+        for index in 0..10 {
+            let _ = unwrap_result!(reader.read(index * READ_SIZE, READ_SIZE));
         }
+        assert_eq!(storage.get_count.load(Ordering::SeqCst), chunk_count);
     }
 }
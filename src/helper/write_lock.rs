@@ -0,0 +1,89 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+use std::sync::{Arc, Mutex};
+
+use errors::NfsError;
+use helper::directory_helper::DirectoryHelper;
+use metadata::directory_key::DirectoryKey;
+use safe_core::client::Client;
+
+/// WriteLock is held on a file obtained using `FileHelper::lock`. While it is held, the content
+/// of the file can be updated only using `FileHelper::update_content_with_lock`. The lock is
+/// released when the WriteLock is dropped, which updates the parent directory in the network.
+/// If that update fails, or the WriteLock is never dropped, the lock stays on the file until it is
+/// cleared using `FileHelper::force_unlock`
+pub struct WriteLock {
+    client: Arc<Mutex<Client>>,
+    directory_key: DirectoryKey,
+    file_name: String,
+    token: String,
+}
+
+impl WriteLock {
+    /// Create a new instance of WriteLock for the lock `token` recorded in the metadata of the
+    /// file named `file_name` in the directory identified by `directory_key`
+    pub fn new(client: Arc<Mutex<Client>>,
+               directory_key: DirectoryKey,
+               file_name: String,
+               token: String)
+               -> WriteLock {
+        WriteLock {
+            client: client,
+            directory_key: directory_key,
+            file_name: file_name,
+            token: token,
+        }
+    }
+
+    /// Returns the name of the locked file
+    pub fn get_file_name(&self) -> &String {
+        &self.file_name
+    }
+
+    /// Returns the token recorded in the metadata of the locked file
+    pub fn get_token(&self) -> &str {
+        &self.token
+    }
+
+    /// Clears the lock token from the latest version of the file, unless the file no longer holds
+    /// this lock
+    fn release(&self) -> Result<(), NfsError> {
+        let directory_helper = DirectoryHelper::new(self.client.clone());
+        let mut directory = try!(directory_helper.get(&self.directory_key));
+        let mut file = try!(directory.find_file(&self.file_name)
+                                     .cloned()
+                                     .ok_or(NfsError::FileNotFound));
+        if file.get_metadata().get_lock_token() != Some(&self.token[..]) {
+            return Ok(());
+        }
+        debug!("Releasing write lock on file {:?} ...", self.file_name);
+        file.get_mut_metadata().set_lock_token(None);
+        directory.upsert_file(file);
+        let _ = try!(directory_helper.update(&directory));
+        Ok(())
+    }
+}
+
+impl Drop for WriteLock {
+    fn drop(&mut self) {
+        if let Err(error) = self.release() {
+            warn!("Failed to release write lock on file {:?} - {:?}",
+                  self.file_name,
+                  error);
+        }
+    }
+}
@@ -15,14 +15,26 @@
 // Please review the Licences for the specific language governing permissions and limitations
 // relating to use of the SAFE Network Software.
 
+use std::io;
 use std::sync::{Arc, Mutex};
 
 use errors::NfsError;
 use directory_listing::DirectoryListing;
 use file::File;
+use routing::{DataRequest, ImmutableDataType};
+#[cfg(feature = "json")]
+use rustc_serialize::Encodable;
+#[cfg(feature = "json")]
+use rustc_serialize::json;
 use safe_core::client::Client;
 use safe_core::SelfEncryptionStorage;
 use self_encryption::{DataMap, SelfEncryptor};
+use tiny_keccak::Keccak;
+use utils;
+use xor_name::XorName;
+
+/// Size of the batches in which `Writer::write_zeroes` writes zeros
+pub const ZERO_CHUNK_SIZE: usize = 65536;
 
 /// Mode of the writter
 pub enum Mode {
@@ -30,6 +42,9 @@ pub enum Mode {
     Overwrite,
     /// Will modify the existing data
     Modify,
+    /// Will add data after the end of the existing data. Writes through `std::io::Write` start
+    /// at the current size of the file
+    Append,
 }
 
 /// Writer is used to write contents to a File and especially in chunks if the file happens to be
@@ -39,6 +54,16 @@ pub struct Writer {
     file: File,
     parent_directory: DirectoryListing,
     self_encryptor: SelfEncryptor<SelfEncryptionStorage>,
+    expected_hash: Option<[u8; 32]>,
+    // Hash of the complete content while it has only been written sequentially from the start
+    running_hash: Option<Keccak>,
+    position: u64,
+    bytes_written: u64,
+    initial_progress: u64,
+    expected_size: Option<u64>,
+    progress_handler: Option<Box<Fn(u64, u64)>>,
+    // DataMap of the content being replaced in Mode::Overwrite
+    replaced_datamap: Option<DataMap>,
 }
 
 impl Writer {
@@ -48,48 +73,266 @@ impl Writer {
                parent_directory: DirectoryListing,
                file: File)
                -> Writer {
-        let datamap = match mode {
-            Mode::Modify => file.get_datamap().clone(),
-            Mode::Overwrite => DataMap::None,
+        let (datamap, replaced_datamap) = match mode {
+            Mode::Modify | Mode::Append => (file.get_datamap().clone(), None),
+            Mode::Overwrite => (DataMap::None, Some(file.get_datamap().clone())),
+        };
+        let self_encryptor = SelfEncryptor::new(SelfEncryptionStorage::new(client.clone()),
+                                                datamap);
+        let position = match mode {
+            Mode::Append => self_encryptor.len(),
+            Mode::Modify | Mode::Overwrite => 0,
+        };
+        let running_hash = if self_encryptor.len() == 0 {
+            Some(Keccak::new_sha3_256())
+        } else {
+            None
         };
 
         Writer {
             client: client.clone(),
             file: file,
             parent_directory: parent_directory,
-            self_encryptor: SelfEncryptor::new(SelfEncryptionStorage::new(client.clone()), datamap),
+            self_encryptor: self_encryptor,
+            expected_hash: None,
+            running_hash: running_hash,
+            position: position,
+            bytes_written: 0,
+            // In Mode::Append the existing content counts towards the progress
+            initial_progress: position,
+            expected_size: None,
+            progress_handler: None,
+            replaced_datamap: replaced_datamap,
         }
     }
 
     /// Data of a file/blob can be written in smaller chunks
+    /// Writes through `std::io::Write` continue from the end of the last write
     pub fn write(&mut self, data: &[u8], position: u64) {
         debug!("Writing file data at position {:?} ...", position);
+        self.update_running_hash(data, position);
         self.self_encryptor.write(data, position);
+        self.position = position + data.len() as u64;
+        self.bytes_written += data.len() as u64;
+        self.report_progress();
+    }
+
+    /// Writes `count` zeros starting at `position`, in batches of ZERO_CHUNK_SIZE bytes
+    pub fn write_zeroes(&mut self, count: u64, position: u64) {
+        debug!("Writing {:?} zeros at position {:?} ...", count, position);
+        let zeros = [0u8; ZERO_CHUNK_SIZE];
+        let mut written = 0;
+        while written < count {
+            let length = ::std::cmp::min(count - written, ZERO_CHUNK_SIZE as u64);
+            self.update_running_hash(&zeros[..length as usize], position + written);
+            self.self_encryptor.write(&zeros[..length as usize], position + written);
+            written += length;
+            self.bytes_written += length;
+            self.report_progress();
+        }
+        self.position = position + count;
+    }
+
+    /// Replaces the content of the file/blob with the JSON encoding of `value`
+    #[cfg(feature = "json")]
+    pub fn write_json<T: Encodable>(&mut self, value: &T) -> Result<(), NfsError> {
+        let json = try!(json::encode(value));
+        debug!("Writing JSON encoded file data ...");
+        let _ = self.self_encryptor.truncate(0);
+        self.running_hash = Some(Keccak::new_sha3_256());
+        self.update_running_hash(json.as_bytes(), 0);
+        self.self_encryptor.write(json.as_bytes(), 0);
+        self.position = json.len() as u64;
+        self.bytes_written += json.len() as u64;
+        self.report_progress();
+        Ok(())
+    }
+
+    /// Sets the size the file/blob is expected to have once all the data is written, which is
+    /// passed to the progress handler
+    pub fn set_expected_size(&mut self, expected_size: u64) {
+        self.expected_size = Some(expected_size);
+    }
+
+    /// Sets `handler` to be invoked after each write with (bytes written, expected size). In
+    /// `Mode::Append` the bytes written include the existing content of the file/blob. If the
+    /// expected size is not set using `set_expected_size`, the current size is passed instead
+    pub fn set_progress_handler<F: Fn(u64, u64) + 'static>(&mut self, handler: F) {
+        self.progress_handler = Some(Box::new(handler));
+    }
+
+    /// Sets the size of the file/blob to `new_size`, discarding the content beyond it or extending
+    /// the content with zeros as required
+    pub fn truncate(&mut self, new_size: u64) -> Result<(), NfsError> {
+        debug!("Truncating file data to {:?} bytes ...", new_size);
+        if new_size != self.self_encryptor.len() {
+            self.running_hash = None;
+        }
+        if self.self_encryptor.truncate(new_size) {
+            Ok(())
+        } else {
+            Err(NfsError::Unexpected("Failed to truncate file data".to_string()))
+        }
+    }
+
+    /// Returns the number of bytes written through this Writer so far. Content which was already
+    /// present when the Writer was created in `Mode::Modify` or `Mode::Append` is not counted
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// Returns the current logical size of the file/blob, including any pre-existing content
+    pub fn total_size(&self) -> u64 {
+        self.self_encryptor.len()
+    }
+
+    /// Adds `data` to the running hash if it is written at the end of the content. Otherwise the
+    /// running hash is discarded and the content is read back on close to compute the hash
+    fn update_running_hash(&mut self, data: &[u8], position: u64) {
+        if position != self.self_encryptor.len() {
+            self.running_hash = None;
+        }
+        if let Some(ref mut running_hash) = self.running_hash {
+            running_hash.update(data);
+        }
+    }
+
+    /// Invokes the progress handler, if set
+    fn report_progress(&self) {
+        if let Some(ref handler) = self.progress_handler {
+            handler(self.initial_progress + self.bytes_written,
+                    self.expected_size.unwrap_or(self.self_encryptor.len()));
+        }
+    }
+
+    /// Sets the expected SHA3-256 hash of the complete content of the file/blob. If set, close
+    /// fails with `NfsError::IntegrityCheckFailed` without saving anything when the written
+    /// content does not match it. The hash is updated as data is written sequentially, eg. through
+    /// `write_all`. If data is written at any other position than the end of the content, the
+    /// content is read back on close to compute the hash instead
+    pub fn expected_hash(&mut self, hash: [u8; 32]) {
+        self.expected_hash = Some(hash);
     }
 
     /// close is invoked only after all the data is completely written
-    /// The file/blob is saved only when the close is invoked.
+    /// The file/blob is saved only when the close is invoked. The SHA3-256 hash of the content is
+    /// recorded in the metadata of the file, so it can be verified by `Reader::verify_integrity`.
     /// Returns the update DirectoryListing which owns the file and also the updated
     /// DirectoryListing of the file's parent
     /// Returns (files's parent_directory, Option<file's parent_directory's parent>)
     pub fn close(self) -> Result<(DirectoryListing, Option<DirectoryListing>), NfsError> {
+        self.close_impl(false)
+    }
+
+    /// Same as `close`, but in `Mode::Overwrite` the chunks of the replaced content which are not
+    /// part of the new content are also deleted from the network, once the new DataMap is saved.
+    /// Deletion is best-effort: failures are logged and do not fail the close.
+    /// Chunks are de-duplicated by the network, so this must not be used if other files may
+    /// have the same content as the replaced content. In particular, copies made through
+    /// `FileHelper::copy_file`, `DirectoryHelper::copy_directory` or `DirectoryHelper::deep_clone`
+    /// share the chunks of the original and would be left unreadable.
+    /// Older versions of a file in a versioned directory keep referring to the replaced content,
+    /// so `NfsError::ParameterIsNotValid` is returned if the parent directory is versioned
+    pub fn close_and_purge(self)
+                           -> Result<(DirectoryListing, Option<DirectoryListing>), NfsError> {
+        self.close_impl(true)
+    }
+
+    fn close_impl(mut self,
+                  purge: bool)
+                  -> Result<(DirectoryListing, Option<DirectoryListing>), NfsError> {
+        if purge && self.parent_directory.get_key().is_versioned() {
+            return Err(NfsError::ParameterIsNotValid);
+        }
+        let size = self.self_encryptor.len();
+        let checksum = match self.running_hash.take() {
+            Some(running_hash) => {
+                let mut checksum = [0u8; 32];
+                running_hash.finalize(&mut checksum);
+                checksum
+            }
+            None => {
+                debug!("Reading back file content to compute its hash ...");
+                let self_encryptor = &mut self.self_encryptor;
+                try!(utils::sha3_256_by_slices(size, |position, length| {
+                    Ok(self_encryptor.read(position, length))
+                }))
+            }
+        };
+        if self.expected_hash.map_or(false, |expected_hash| expected_hash != checksum) {
+            return Err(NfsError::IntegrityCheckFailed);
+        }
         let mut file = self.file;
         let mut directory = self.parent_directory;
-        let size = self.self_encryptor.len();
 
         file.set_datamap(self.self_encryptor.close());
+        file.increment_version();
 
         file.get_mut_metadata().set_modified_time(::time::now_utc());
         file.get_mut_metadata().set_size(size);
+        file.get_mut_metadata().set_checksum(Some(checksum));
 
         directory.upsert_file(file.clone());
 
         let directory_helper = ::helper::directory_helper::DirectoryHelper::new(self.client
                                                                                     .clone());
-        if let Some(updated_grand_parent) = try!(directory_helper.update(&directory)) {
-            Ok((directory, Some(updated_grand_parent)))
-        } else {
-            Ok((directory, None))
+        let updated_grand_parent = try!(directory_helper.update(&directory));
+
+        // The replaced chunks are only deleted once nothing refers to them any more
+        if purge {
+            if let Some(ref replaced_datamap) = self.replaced_datamap {
+                purge_chunks(&self.client, replaced_datamap, file.get_datamap());
+            }
         }
+        Ok((directory, updated_grand_parent))
+    }
+}
+
+/// Deletes the chunks of `old_datamap` which are not part of `new_datamap` from the network.
+/// Failures are logged and otherwise ignored
+fn purge_chunks(client: &Arc<Mutex<Client>>, old_datamap: &DataMap, new_datamap: &DataMap) {
+    let old_chunks = match *old_datamap {
+        DataMap::Chunks(ref chunks) => chunks,
+        _ => return,
+    };
+    let retained: Vec<&Vec<u8>> = match *new_datamap {
+        DataMap::Chunks(ref chunks) => chunks.iter().map(|chunk| &chunk.hash).collect(),
+        _ => Vec::new(),
+    };
+    for chunk in old_chunks.iter().filter(|chunk| !retained.contains(&&chunk.hash)) {
+        debug!("Deleting replaced chunk {:?} ...", chunk.chunk_num);
+        if let Err(error) = delete_chunk(client, &chunk.hash) {
+            warn!("Failed to delete replaced chunk {:?}: {:?}",
+                  chunk.chunk_num,
+                  error);
+        }
+    }
+}
+
+/// Deletes the chunk named `name` from the network
+fn delete_chunk(client: &Arc<Mutex<Client>>, name: &[u8]) -> Result<(), NfsError> {
+    if name.len() != 64 {
+        return Err(NfsError::Unexpected("Invalid chunk name".to_string()));
+    }
+    let mut name_id = [0u8; 64];
+    for (dst, src) in name_id.iter_mut().zip(name) {
+        *dst = *src;
+    }
+    let request = DataRequest::Immutable(XorName::new(name_id), ImmutableDataType::Normal);
+    let response_getter = try!(unwrap_result!(client.lock()).get(request, None));
+    let data = try!(response_getter.get());
+    Ok(try!(unwrap_result!(client.lock()).delete(data, None)))
+}
+
+impl io::Write for Writer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let position = self.position;
+        Writer::write(self, buf, position);
+        Ok(buf.len())
+    }
+
+    /// Data is saved only on close, so there is nothing to flush
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
     }
 }
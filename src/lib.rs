@@ -48,10 +48,13 @@ extern crate safe_core;
 extern crate sodiumoxide;
 extern crate rustc_serialize;
 extern crate self_encryption;
+extern crate tiny_keccak;
 #[macro_use]
 extern crate log;
 #[macro_use]
 extern crate maidsafe_utilities;
+#[cfg(test)]
+extern crate rand;
 
 /// Module for File struct
 pub mod file;
@@ -65,21 +68,40 @@ pub mod helper;
 pub mod metadata;
 /// Module for directory reltaed structs - DirectoryListin, DirectoryInfo
 pub mod directory_listing;
+/// Utility functions
+pub mod utils;
 
 /// Root directory name
 pub const ROOT_DIRECTORY_NAME: &'static str = "USER_ROOT";
 /// Configuration directory Name stored in the session packet
 pub const CONFIGURATION_DIRECTORY_NAME: &'static str = "CONFIGURATION_ROOT";
+/// Name of the sub directory into which files are archived
+pub const ARCHIVE_DIRECTORY_NAME: &'static str = "_archive";
+/// Name of the sub directory holding temporary files
+pub const TEMPORARY_DIRECTORY_NAME: &'static str = "_temp";
 /// Tag representing the Versioned Directory Listing
 pub const VERSIONED_DIRECTORY_LISTING_TAG: u64 = safe_core::CLIENT_STRUCTURED_DATA_TAG + 100;
 /// Tag representing the Versioned Directory Listing
 pub const UNVERSIONED_DIRECTORY_LISTING_TAG: u64 = VERSIONED_DIRECTORY_LISTING_TAG + 1;
 
-/// AccessLevel indicates whether the container is Private or Public shared
+/// AccessLevel indicates whether the container is Private, Public or Public shared for writing
 #[derive(RustcEncodable, RustcDecodable, PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
 pub enum AccessLevel {
     /// Private Directory where the directory is encrypted with users private keys
     Private,
     /// Public Directory where the directory is not encrypted and anyone can read the contents of it
     Public,
+    /// Public Directory which is not encrypted and which any network participant who knows its
+    /// DirectoryKey may modify, as it is signed with a key pair derived from its id
+    PublicReadWrite,
+}
+
+impl ::std::fmt::Display for AccessLevel {
+    fn fmt(&self, formatter: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        match *self {
+            AccessLevel::Private => write!(formatter, "Private"),
+            AccessLevel::Public => write!(formatter, "Public"),
+            AccessLevel::PublicReadWrite => write!(formatter, "PublicReadWrite"),
+        }
+    }
 }
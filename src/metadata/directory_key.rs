@@ -15,6 +15,11 @@
 // Please review the Licences for the specific language governing permissions and limitations
 // relating to use of the SAFE Network Software.
 
+use std::fmt;
+
+use errors::NfsError;
+use maidsafe_utilities::serialisation::{serialise, deserialise};
+use sodiumoxide::crypto::auth::hmacsha512;
 use xor_name::XorName;
 
 /// DirectoryKey represnts the meta information about a directory
@@ -58,6 +63,93 @@ impl DirectoryKey {
     pub fn get_access_level(&self) -> &::AccessLevel {
         &self.access_level
     }
+
+    /// Derives the key of a child directory deterministically from this key and `child_name`.
+    /// The id is the HMAC-SHA-512 of `child_name` keyed with the id of this key, while the
+    /// type_tag, versioning and AccessLevel are inherited from this key.
+    /// HMAC-SHA-512 is used rather than HMAC-SHA256 since an id is a 64 byte XorName, which the
+    /// 32 byte HMAC-SHA256 tag can not fill. Ids derived using HMAC-SHA256 do not match these
+    pub fn derive_child_key(&self, child_name: &str) -> DirectoryKey {
+        let mut state = hmacsha512::State::init(&self.id.0);
+        state.update(child_name.as_bytes());
+        let hmacsha512::Tag(id) = state.finalize();
+        DirectoryKey::new(XorName::new(id),
+                          self.type_tag,
+                          self.versioned,
+                          self.access_level.clone())
+    }
+
+    /// Serialises the DirectoryKey to CBOR
+    pub fn to_cbor_bytes(&self) -> Result<Vec<u8>, NfsError> {
+        Ok(try!(serialise(self)))
+    }
+
+    /// Deserialises a DirectoryKey from the CBOR produced by `to_cbor_bytes`
+    pub fn from_cbor_bytes(data: Vec<u8>) -> Result<DirectoryKey, NfsError> {
+        Ok(try!(deserialise(&data)))
+    }
+}
+
+/// Builds a DirectoryKey from named fields, as an alternative to `DirectoryKey::new`
+#[derive(Debug, Default, Clone)]
+pub struct DirectoryKeyBuilder {
+    id: Option<XorName>,
+    type_tag: Option<u64>,
+    versioned: Option<bool>,
+    access_level: Option<::AccessLevel>,
+}
+
+impl DirectoryKeyBuilder {
+    /// Creates a new instance of DirectoryKeyBuilder with none of the fields set
+    pub fn new() -> DirectoryKeyBuilder {
+        DirectoryKeyBuilder::default()
+    }
+
+    /// Sets the id of the directory
+    pub fn id(mut self, id: XorName) -> DirectoryKeyBuilder {
+        self.id = Some(id);
+        self
+    }
+
+    /// Sets the type_tag of the directory
+    pub fn type_tag(mut self, type_tag: u64) -> DirectoryKeyBuilder {
+        self.type_tag = Some(type_tag);
+        self
+    }
+
+    /// Sets whether the directory is versioned
+    pub fn versioned(mut self, versioned: bool) -> DirectoryKeyBuilder {
+        self.versioned = Some(versioned);
+        self
+    }
+
+    /// Sets the AccessLevel of the directory
+    pub fn access_level(mut self, access_level: ::AccessLevel) -> DirectoryKeyBuilder {
+        self.access_level = Some(access_level);
+        self
+    }
+
+    /// Builds the DirectoryKey. Returns `NfsError::ParameterIsNotValid` if any of the fields is
+    /// not set
+    pub fn build(self) -> Result<DirectoryKey, NfsError> {
+        match (self.id, self.type_tag, self.versioned, self.access_level) {
+            (Some(id), Some(type_tag), Some(versioned), Some(access_level)) => {
+                Ok(DirectoryKey::new(id, type_tag, versioned, access_level))
+            }
+            _ => Err(NfsError::ParameterIsNotValid),
+        }
+    }
+}
+
+impl fmt::Display for DirectoryKey {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter,
+               "DirectoryKey {{ id: {}, tag: {}, versioned: {}, access: {} }}",
+               &self.id.as_hex()[..8],
+               self.type_tag,
+               self.versioned,
+               self.access_level)
+    }
 }
 
 #[cfg(test)]
@@ -66,6 +158,7 @@ mod test {
     use xor_name::XorName;
     use maidsafe_utilities::serialisation::{serialise, deserialise};
     use safe_core::utility;
+    use utils::test::verify_roundtrip;
 
     /// Should be able to serialise & deserialise the DirectoryKey
     #[test]
@@ -84,5 +177,65 @@ mod test {
         assert_eq!(*deserilaised_key.get_access_level(), access_level);
         assert_eq!(deserilaised_key.is_versioned(), versioned);
         assert_eq!(deserilaised_key.get_type_tag(), tag);
+
+        verify_roundtrip(&directory_key);
+
+        let serialised = unwrap_result!(directory_key.to_cbor_bytes());
+        assert_eq!(unwrap_result!(DirectoryKey::from_cbor_bytes(serialised)), directory_key);
+    }
+
+    #[test]
+    fn derive_child_key() {
+        let id = XorName(unwrap_result!(utility::generate_random_array_u8_64()));
+        let parent = DirectoryKey::new(id, 10u64, true, ::AccessLevel::Public);
+
+        let inbox = parent.derive_child_key("inbox");
+        assert_eq!(inbox, parent.derive_child_key("inbox"));
+        assert!(inbox.get_id() != parent.get_id());
+        assert!(inbox.get_id() != parent.derive_child_key("sent").get_id());
+        assert_eq!(inbox.get_type_tag(), parent.get_type_tag());
+        assert_eq!(inbox.is_versioned(), parent.is_versioned());
+        assert_eq!(inbox.get_access_level(), parent.get_access_level());
+
+        let other_id = XorName(unwrap_result!(utility::generate_random_array_u8_64()));
+        let other_parent = DirectoryKey::new(other_id, 10u64, true, ::AccessLevel::Public);
+        assert!(other_parent.derive_child_key("inbox").get_id() != inbox.get_id());
+    }
+
+    #[test]
+    fn build_directory_key() {
+        let id = XorName(unwrap_result!(utility::generate_random_array_u8_64()));
+        let builder = DirectoryKeyBuilder::new()
+                          .id(id.clone())
+                          .type_tag(10u64)
+                          .access_level(::AccessLevel::Public);
+        assert!(builder.clone().build().is_err());
+
+        let directory_key = unwrap_result!(builder.versioned(true).build());
+        assert_eq!(directory_key, DirectoryKey::new(id, 10u64, true, ::AccessLevel::Public));
+    }
+
+    #[test]
+    fn display_directory_key() {
+        let id = XorName([0xab; 64]);
+        let directory_key = DirectoryKey::new(id, 102u64, true, ::AccessLevel::Private);
+        assert_eq!(format!("{}", directory_key),
+                   "DirectoryKey { id: abababab, tag: 102, versioned: true, access: Private }");
+
+        let id = XorName(unwrap_result!(utility::generate_random_array_u8_64()));
+        let directory_key = DirectoryKey::new(id, 10u64, false, ::AccessLevel::Public);
+        let displayed = format!("{}", directory_key);
+        let prefix = format!("DirectoryKey {{ id: {}, ", &id.as_hex()[..8]);
+        assert!(displayed.starts_with(&prefix));
+        assert!(displayed.ends_with("tag: 10, versioned: false, access: Public }"));
+        assert_eq!(format!("{}", ::AccessLevel::Public), "Public");
+    }
+
+    #[test]
+    fn serialise_public_read_write_directory_key() {
+        let id = XorName(unwrap_result!(utility::generate_random_array_u8_64()));
+        let directory_key = DirectoryKey::new(id, 10u64, true, ::AccessLevel::PublicReadWrite);
+        verify_roundtrip(&directory_key);
+        assert_eq!(format!("{}", ::AccessLevel::PublicReadWrite), "PublicReadWrite");
     }
 }
@@ -21,6 +21,9 @@ use xor_name::XorName;
 use metadata::directory_key::DirectoryKey;
 use safe_core::utility;
 
+/// Maximum size in bytes of the description of a directory
+pub const MAX_DESCRIPTION_SIZE: usize = 1024;
+
 /// Metadata about a File or a Directory
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
 pub struct DirectoryMetadata {
@@ -30,6 +33,8 @@ pub struct DirectoryMetadata {
     modified_time: ::time::Tm,
     user_metadata: Vec<u8>,
     parent_dir_key: Option<DirectoryKey>,
+    description: Option<String>,
+    quota: Option<u64>,
 }
 
 impl DirectoryMetadata {
@@ -49,6 +54,8 @@ impl DirectoryMetadata {
             modified_time: ::time::now_utc(),
             user_metadata: user_metadata,
             parent_dir_key: parent_dir_key,
+            description: None,
+            quota: None,
         })
     }
 
@@ -103,6 +110,16 @@ impl DirectoryMetadata {
         &self.user_metadata
     }
 
+    /// Get the human readable description of the directory
+    pub fn get_description(&self) -> Option<&str> {
+        self.description.as_ref().map(|description| &description[..])
+    }
+
+    /// Get the maximum total size in bytes of the files in the directory, if limited
+    pub fn get_quota(&self) -> Option<u64> {
+        self.quota
+    }
+
     /// Set name associated with the structure (file or directory) that this metadata is a part
     /// of
     pub fn set_name(&mut self, name: String) {
@@ -128,6 +145,16 @@ impl DirectoryMetadata {
     pub fn set_parent_dir_key(&mut self, parent_dir_key: Option<DirectoryKey>) {
         self.parent_dir_key = parent_dir_key;
     }
+
+    /// Set the human readable description of the directory
+    pub fn set_description(&mut self, description: Option<String>) {
+        self.description = description;
+    }
+
+    /// Set the maximum total size in bytes of the files in the directory. None removes the limit
+    pub fn set_quota(&mut self, quota: Option<u64>) {
+        self.quota = quota;
+    }
 }
 
 impl ::rustc_serialize::Encodable for DirectoryMetadata {
@@ -135,7 +162,7 @@ impl ::rustc_serialize::Encodable for DirectoryMetadata {
         let created_time = self.created_time.to_timespec();
         let modified_time = self.modified_time.to_timespec();
 
-        e.emit_struct("DirectoryMetadata", 8, |e| {
+        e.emit_struct("DirectoryMetadata", 10, |e| {
             try!(e.emit_struct_field("key", 0, |e| self.key.encode(e)));
             try!(e.emit_struct_field("name", 1, |e| self.name.encode(e)));
             try!(e.emit_struct_field("created_time_sec", 2, |e| created_time.sec.encode(e)));
@@ -144,6 +171,8 @@ impl ::rustc_serialize::Encodable for DirectoryMetadata {
             try!(e.emit_struct_field("modified_time_nsec", 5, |e| modified_time.nsec.encode(e)));
             try!(e.emit_struct_field("user_metadata", 6, |e| self.user_metadata.encode(e)));
             try!(e.emit_struct_field("parent_dir_key", 7, |e| self.parent_dir_key.encode(e)));
+            try!(e.emit_struct_field("description", 8, |e| self.description.encode(e)));
+            try!(e.emit_struct_field("quota", 9, |e| self.quota.encode(e)));
 
             Ok(())
         })
@@ -151,8 +180,10 @@ impl ::rustc_serialize::Encodable for DirectoryMetadata {
 }
 
 impl Decodable for DirectoryMetadata {
+    // description and quota were added after the initial format. An absent field is decoded as
+    // nil, so they are read as None from metadata stored by earlier versions of the crate
     fn decode<D: Decoder>(d: &mut D) -> Result<DirectoryMetadata, D::Error> {
-        d.read_struct("DirectoryMetadata", 8, |d| {
+        d.read_struct("DirectoryMetadata", 10, |d| {
             Ok(DirectoryMetadata {
                 key: try!(d.read_struct_field("key", 0, |d| Decodable::decode(d))),
                 name: try!(d.read_struct_field("name", 1, |d| Decodable::decode(d))),
@@ -176,6 +207,10 @@ impl Decodable for DirectoryMetadata {
                 parent_dir_key: try!(d.read_struct_field("parent_dir_key",
                                                          7,
                                                          |d| Decodable::decode(d))),
+                description: try!(d.read_struct_field("description",
+                                                      8,
+                                                      |d| Decodable::decode(d))),
+                quota: try!(d.read_struct_field("quota", 9, |d| Decodable::decode(d))),
             })
         })
     }
@@ -187,8 +222,60 @@ mod test {
     use xor_name::XorName;
     use metadata::directory_key::DirectoryKey;
     use maidsafe_utilities::serialisation::{serialise, deserialise};
+    use rustc_serialize::{Encodable, Encoder};
     use safe_core::utility;
 
+    // DirectoryMetadata as encoded by the initial version of the crate
+    struct BaselineDirectoryMetadata {
+        key: DirectoryKey,
+        name: String,
+        parent_dir_key: Option<DirectoryKey>,
+    }
+
+    impl Encodable for BaselineDirectoryMetadata {
+        fn encode<E: Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
+            e.emit_struct("DirectoryMetadata", 8, |e| {
+                try!(e.emit_struct_field("key", 0, |e| self.key.encode(e)));
+                try!(e.emit_struct_field("name", 1, |e| self.name.encode(e)));
+                try!(e.emit_struct_field("created_time_sec", 2, |e| 1i64.encode(e)));
+                try!(e.emit_struct_field("created_time_nsec", 3, |e| 2i32.encode(e)));
+                try!(e.emit_struct_field("modified_time_sec", 4, |e| 3i64.encode(e)));
+                try!(e.emit_struct_field("modified_time_nsec", 5, |e| 4i32.encode(e)));
+                try!(e.emit_struct_field("user_metadata", 6, |e| vec![1u8].encode(e)));
+                try!(e.emit_struct_field("parent_dir_key", 7, |e| self.parent_dir_key.encode(e)));
+                Ok(())
+            })
+        }
+    }
+
+    #[test]
+    fn deserialise_baseline_directory_metadata() {
+        let id = XorName::new(unwrap_result!((utility::generate_random_array_u8_64())));
+        let key = DirectoryKey::new(id, 99u64, true, ::AccessLevel::Private);
+        let id = XorName::new(unwrap_result!((utility::generate_random_array_u8_64())));
+        let parent_key = DirectoryKey::new(id, 100u64, false, ::AccessLevel::Public);
+        for parent_dir_key in vec![None, Some(parent_key)] {
+            let baseline = BaselineDirectoryMetadata {
+                key: key.clone(),
+                name: "Home".to_string(),
+                parent_dir_key: parent_dir_key.clone(),
+            };
+            let metadata: DirectoryMetadata =
+                unwrap_result!(deserialise(&unwrap_result!(serialise(&baseline))));
+            assert_eq!(*metadata.get_key(), key);
+            assert_eq!(*metadata.get_name(), "Home".to_string());
+            assert_eq!(metadata.get_created_time().to_timespec(),
+                       ::time::Timespec {
+                           sec: 1,
+                           nsec: 2,
+                       });
+            assert_eq!(*metadata.get_user_metadata(), vec![1u8]);
+            assert_eq!(metadata.get_parent_dir_key(), parent_dir_key.as_ref());
+            assert_eq!(metadata.get_description(), None);
+            assert_eq!(metadata.get_quota(), None);
+        }
+    }
+
     #[test]
     fn serialise_directorty_metadata_without_parent_directory() {
         let obj_before = unwrap_result!(DirectoryMetadata::new("hello.txt".to_string(),
@@ -232,10 +319,14 @@ mod test {
         obj_before.set_user_metadata(user_metadata.clone());
         obj_before.set_modified_time(modified_time.clone());
         obj_before.set_name("index.txt".to_string());
+        obj_before.set_description(Some("Text files".to_string()));
+        obj_before.set_quota(Some(1000));
         let serialised_data = unwrap_result!(serialise(&obj_before));
         let obj_after: DirectoryMetadata = unwrap_result!(deserialise(&serialised_data));
         assert_eq!(user_metadata, *obj_after.get_user_metadata());
         assert_eq!(modified_time, *obj_after.get_modified_time());
         assert_eq!("index.txt".to_string(), *obj_after.get_name());
+        assert_eq!(obj_after.get_description(), Some("Text files"));
+        assert_eq!(obj_after.get_quota(), Some(1000));
     }
 }
@@ -15,8 +15,16 @@
 // Please review the Licences for the specific language governing permissions and limitations
 // relating to use of the SAFE Network Software.
 
+use std::collections::BTreeMap;
+
 use rustc_serialize::{Encodable, Encoder, Decodable, Decoder};
 use time::{self, Timespec, Tm};
+use utils;
+
+/// Maximum size of a tag in bytes
+pub const MAX_TAG_SIZE: usize = 64;
+/// Version of the FileMetadata schema written by this version of the crate
+pub const CURRENT_SCHEMA_VERSION: u32 = 1;
 
 /// FileMetadata about a File or a Directory
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
@@ -26,6 +34,15 @@ pub struct FileMetadata {
     created_time: Tm,
     modified_time: Tm,
     user_metadata: Vec<u8>,
+    recipient_encrypted: bool,
+    tags: Vec<String>,
+    mime_type: Option<String>,
+    schema_version: u32,
+    content_encrypted: bool,
+    expiry_time: Option<Tm>,
+    tag_values: BTreeMap<String, Vec<u8>>,
+    checksum: Option<[u8; 32]>,
+    lock_token: Option<String>,
 }
 
 impl FileMetadata {
@@ -37,6 +54,15 @@ impl FileMetadata {
             created_time: time::now_utc(),
             modified_time: time::now_utc(),
             user_metadata: user_metadata,
+            recipient_encrypted: false,
+            tags: Vec::new(),
+            mime_type: None,
+            schema_version: CURRENT_SCHEMA_VERSION,
+            content_encrypted: false,
+            expiry_time: None,
+            tag_values: BTreeMap::new(),
+            checksum: None,
+            lock_token: None,
         }
     }
 
@@ -66,6 +92,56 @@ impl FileMetadata {
         &self.user_metadata
     }
 
+    /// Returns true if the user metadata holds the data-map of the file encrypted for a recipient
+    pub fn is_recipient_encrypted(&self) -> bool {
+        self.recipient_encrypted
+    }
+
+    /// Get the tags of the file
+    pub fn get_tags(&self) -> &Vec<String> {
+        &self.tags
+    }
+
+    /// Get the MIME type of the file content, if known
+    pub fn get_mime_type(&self) -> Option<&str> {
+        self.mime_type.as_ref().map(|mime_type| &mime_type[..])
+    }
+
+    /// Get the version of the schema the metadata was written under
+    pub fn get_schema_version(&self) -> u32 {
+        self.schema_version
+    }
+
+    /// Returns true if the content of the file is encrypted with a symmetric key
+    pub fn is_content_encrypted(&self) -> bool {
+        self.content_encrypted
+    }
+
+    /// Get the time after which the file can be discarded, if any
+    pub fn get_expiry_time(&self) -> Option<&Tm> {
+        self.expiry_time.as_ref()
+    }
+
+    /// Get the value stored against the tag `key`, if any
+    pub fn get_tag_value(&self, key: &str) -> Option<&[u8]> {
+        self.tag_values.get(key).map(|value| &value[..])
+    }
+
+    /// Get all the tag values of the file. These are independent of the tags returned by
+    /// `get_tags`
+    pub fn get_tag_values(&self) -> &BTreeMap<String, Vec<u8>> {
+        &self.tag_values
+    }
+
+    /// Get the SHA3-256 hash of the content of the file recorded when it was last written, if any
+    pub fn get_checksum(&self) -> Option<&[u8; 32]> {
+        self.checksum.as_ref()
+    }
+
+    /// Get the token of the write lock held on the file, if any
+    pub fn get_lock_token(&self) -> Option<&str> {
+        self.lock_token.as_ref().map(|lock_token| &lock_token[..])
+    }
 
     /// Set name associated with the structure (file or directory)
     pub fn set_name(&mut self, name: String) {
@@ -77,6 +153,11 @@ impl FileMetadata {
         self.size = size;
     }
 
+    /// Set time of creation
+    pub fn set_created_time(&mut self, created_time: Tm) {
+        self.created_time = created_time
+    }
+
     /// Set time of modification
     pub fn set_modified_time(&mut self, modified_time: Tm) {
         self.modified_time = modified_time
@@ -86,14 +167,73 @@ impl FileMetadata {
     pub fn set_user_metadata(&mut self, user_metadata: Vec<u8>) {
         self.user_metadata = user_metadata;
     }
+
+    /// Set whether the user metadata holds the data-map of the file encrypted for a recipient
+    pub fn set_recipient_encrypted(&mut self, recipient_encrypted: bool) {
+        self.recipient_encrypted = recipient_encrypted;
+    }
+
+    /// Set the tags of the file
+    pub fn set_tags(&mut self, tags: Vec<String>) {
+        self.tags = tags;
+    }
+
+    /// Set the MIME type of the file content
+    pub fn set_mime_type(&mut self, mime_type: String) {
+        self.mime_type = Some(mime_type);
+    }
+
+    /// Set the version of the schema the metadata conforms to
+    pub fn set_schema_version(&mut self, schema_version: u32) {
+        self.schema_version = schema_version;
+    }
+
+    /// Set whether the content of the file is encrypted with a symmetric key
+    pub fn set_content_encrypted(&mut self, content_encrypted: bool) {
+        self.content_encrypted = content_encrypted;
+    }
+
+    /// Set the time after which the file can be discarded
+    pub fn set_expiry_time(&mut self, expiry_time: Tm) {
+        self.expiry_time = Some(expiry_time);
+    }
+
+    /// Remove the expiry time, so that the file is never discarded
+    pub fn clear_expiry_time(&mut self) {
+        self.expiry_time = None;
+    }
+
+    /// Store `value` against the tag `key`, replacing any previous value
+    pub fn set_tag_value(&mut self, key: String, value: Vec<u8>) {
+        let _ = self.tag_values.insert(key, value);
+    }
+
+    /// Remove the tag `key`, returning its value if it was present
+    pub fn remove_tag_value(&mut self, key: &str) -> Option<Vec<u8>> {
+        self.tag_values.remove(key)
+    }
+
+    /// Set the SHA3-256 hash of the content of the file
+    pub fn set_checksum(&mut self, checksum: Option<[u8; 32]>) {
+        self.checksum = checksum;
+    }
+
+    /// Set the token of the write lock held on the file
+    pub fn set_lock_token(&mut self, lock_token: Option<String>) {
+        self.lock_token = lock_token;
+    }
 }
 
 impl Encodable for FileMetadata {
     fn encode<E: Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
         let created_time = self.created_time.to_timespec();
         let modified_time = self.modified_time.to_timespec();
+        let expiry_time = self.expiry_time.map(|expiry_time| {
+            let expiry_time = expiry_time.to_timespec();
+            (expiry_time.sec, expiry_time.nsec)
+        });
 
-        e.emit_struct("FileMetadata", 7, |e| {
+        e.emit_struct("FileMetadata", 16, |e| {
             try!(e.emit_struct_field("name", 0, |e| self.name.encode(e)));
             try!(e.emit_struct_field("size", 1, |e| self.size.encode(e)));
             try!(e.emit_struct_field("created_time_sec", 2, |e| created_time.sec.encode(e)));
@@ -101,6 +241,19 @@ impl Encodable for FileMetadata {
             try!(e.emit_struct_field("modified_time_sec", 4, |e| modified_time.sec.encode(e)));
             try!(e.emit_struct_field("modified_time_nsec", 5, |e| modified_time.nsec.encode(e)));
             try!(e.emit_struct_field("user_metadata", 6, |e| self.user_metadata.encode(e)));
+            try!(e.emit_struct_field("recipient_encrypted",
+                                     7,
+                                     |e| self.recipient_encrypted.encode(e)));
+            try!(e.emit_struct_field("tags", 8, |e| self.tags.encode(e)));
+            try!(e.emit_struct_field("mime_type", 9, |e| self.mime_type.encode(e)));
+            try!(e.emit_struct_field("schema_version", 10, |e| self.schema_version.encode(e)));
+            try!(e.emit_struct_field("content_encrypted",
+                                     11,
+                                     |e| self.content_encrypted.encode(e)));
+            try!(e.emit_struct_field("expires_at", 12, |e| expiry_time.encode(e)));
+            try!(e.emit_struct_field("tag_values", 13, |e| self.tag_values.encode(e)));
+            try!(e.emit_struct_field("checksum", 14, |e| self.checksum.encode(e)));
+            try!(e.emit_struct_field("lock_token", 15, |e| self.lock_token.encode(e)));
 
             Ok(())
         })
@@ -108,8 +261,11 @@ impl Encodable for FileMetadata {
 }
 
 impl Decodable for FileMetadata {
+    // Only the fields up to user_metadata were present in the initial format. The fields added
+    // later are decoded with defaults when absent, Option fields as None, so that metadata
+    // stored by earlier versions of the crate can still be read
     fn decode<D: Decoder>(d: &mut D) -> Result<FileMetadata, D::Error> {
-        d.read_struct("FileMetadata", 7, |d| {
+        d.read_struct("FileMetadata", 16, |d| {
             Ok(FileMetadata {
                 name: try!(d.read_struct_field("name", 0, |d| Decodable::decode(d))),
                 size: try!(d.read_struct_field("size", 1, |d| Decodable::decode(d))),
@@ -130,6 +286,28 @@ impl Decodable for FileMetadata {
                 user_metadata: try!(d.read_struct_field("user_metadata",
                                                         6,
                                                         |d| Decodable::decode(d))),
+                recipient_encrypted: try!(utils::read_struct_field_or(d,
+                                                                      "recipient_encrypted",
+                                                                      7,
+                                                                      || false)),
+                tags: try!(utils::read_struct_field_or(d, "tags", 8, Vec::new)),
+                mime_type: try!(d.read_struct_field("mime_type", 9, |d| Decodable::decode(d))),
+                // Metadata stored before the schema version was recorded is version 0
+                schema_version: try!(utils::read_struct_field_or(d, "schema_version", 10, || 0)),
+                content_encrypted: try!(utils::read_struct_field_or(d,
+                                                                    "content_encrypted",
+                                                                    11,
+                                                                    || false)),
+                expiry_time: try!(d.read_struct_field("expires_at", 12, |d| Decodable::decode(d)))
+                                 .map(|(sec, nsec): (i64, i32)| {
+                                     ::time::at_utc(Timespec {
+                                         sec: sec,
+                                         nsec: nsec,
+                                     })
+                                 }),
+                tag_values: try!(utils::read_struct_field_or(d, "tag_values", 13, BTreeMap::new)),
+                checksum: try!(d.read_struct_field("checksum", 14, |d| Decodable::decode(d))),
+                lock_token: try!(d.read_struct_field("lock_token", 15, |d| Decodable::decode(d))),
             })
         })
     }
@@ -139,13 +317,108 @@ impl Decodable for FileMetadata {
 mod test {
     use super::*;
     use maidsafe_utilities::serialisation::{serialise, deserialise};
+    use rustc_serialize::{Encodable, Encoder};
+    use time::Timespec;
+    use utils::test::verify_roundtrip;
+
+    // FileMetadata as encoded by the initial version of the crate
+    struct BaselineFileMetadata {
+        name: String,
+        size: u64,
+        user_metadata: Vec<u8>,
+    }
+
+    impl Encodable for BaselineFileMetadata {
+        fn encode<E: Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
+            e.emit_struct("FileMetadata", 7, |e| {
+                try!(e.emit_struct_field("name", 0, |e| self.name.encode(e)));
+                try!(e.emit_struct_field("size", 1, |e| self.size.encode(e)));
+                try!(e.emit_struct_field("created_time_sec", 2, |e| 1i64.encode(e)));
+                try!(e.emit_struct_field("created_time_nsec", 3, |e| 2i32.encode(e)));
+                try!(e.emit_struct_field("modified_time_sec", 4, |e| 3i64.encode(e)));
+                try!(e.emit_struct_field("modified_time_nsec", 5, |e| 4i32.encode(e)));
+                try!(e.emit_struct_field("user_metadata", 6, |e| self.user_metadata.encode(e)));
+                Ok(())
+            })
+        }
+    }
+
+    #[test]
+    fn deserialise_baseline_file_metadata() {
+        let baseline = BaselineFileMetadata {
+            name: "hello.txt".to_string(),
+            size: 10,
+            user_metadata: vec![1u8, 2],
+        };
+        let serialised_baseline = unwrap_result!(serialise(&baseline));
+        let metadata: FileMetadata = unwrap_result!(deserialise(&serialised_baseline));
+        assert_eq!(*metadata.get_name(), "hello.txt".to_string());
+        assert_eq!(metadata.get_size(), 10);
+        assert_eq!(metadata.get_created_time().to_timespec(),
+                   Timespec {
+                       sec: 1,
+                       nsec: 2,
+                   });
+        assert_eq!(metadata.get_modified_time().to_timespec(),
+                   Timespec {
+                       sec: 3,
+                       nsec: 4,
+                   });
+        assert_eq!(*metadata.get_user_metadata(), vec![1u8, 2]);
+        assert!(!metadata.is_recipient_encrypted());
+        assert!(metadata.get_tags().is_empty());
+        assert_eq!(metadata.get_mime_type(), None);
+        assert!(!metadata.is_content_encrypted());
+        assert_eq!(metadata.get_checksum(), None);
+        assert_eq!(metadata.get_lock_token(), None);
+        assert!(metadata.get_tag_values().is_empty());
+        assert!(metadata.get_tag_value("author").is_none());
+        assert_eq!(metadata.get_schema_version(), 0);
+        assert!(metadata.get_expiry_time().is_none());
+    }
 
     #[test]
     fn serialise_and_deserialise_file_metadata() {
-        let obj_before = FileMetadata::new("hello.txt".to_string(),
-                                           "{mime: \"application/json\"}".to_string().into_bytes());
-        let serialised_data = unwrap_result!(serialise(&obj_before));
-        let obj_after = unwrap_result!(deserialise(&serialised_data));
-        assert_eq!(obj_before, obj_after);
+        let mut obj_before = FileMetadata::new("hello.txt".to_string(),
+                                               "{mime: \"application/json\"}"
+                                                   .to_string()
+                                                   .into_bytes());
+        verify_roundtrip(&obj_before);
+        obj_before.set_tags(vec!["work".to_string(), "draft".to_string()]);
+        verify_roundtrip(&obj_before);
+        obj_before.set_mime_type("application/json".to_string());
+        verify_roundtrip(&obj_before);
+        assert_eq!(obj_before.get_schema_version(), CURRENT_SCHEMA_VERSION);
+        obj_before.set_schema_version(0);
+        verify_roundtrip(&obj_before);
+        obj_before.set_content_encrypted(true);
+        verify_roundtrip(&obj_before);
+        obj_before.set_expiry_time(::time::now_utc());
+        verify_roundtrip(&obj_before);
+        obj_before.clear_expiry_time();
+        assert!(obj_before.get_expiry_time().is_none());
+        verify_roundtrip(&obj_before);
+        obj_before.set_expiry_time(::time::now_utc());
+        verify_roundtrip(&obj_before);
+        obj_before.set_tag_value("author".to_string(), b"maidsafe".to_vec());
+        obj_before.set_tag_value("rating".to_string(), vec![5u8]);
+        verify_roundtrip(&obj_before);
+        obj_before.set_checksum(Some([7u8; 32]));
+        verify_roundtrip(&obj_before);
+        obj_before.set_lock_token(Some("token".to_string()));
+        verify_roundtrip(&obj_before);
+    }
+
+    #[test]
+    fn tag_values() {
+        let mut metadata = FileMetadata::new("hello.txt".to_string(), Vec::new());
+        assert!(metadata.get_tag_value("author").is_none());
+        metadata.set_tag_value("author".to_string(), b"first".to_vec());
+        metadata.set_tag_value("author".to_string(), b"second".to_vec());
+        assert_eq!(metadata.get_tag_value("author"), Some(&b"second"[..]));
+        assert_eq!(metadata.get_tag_values().len(), 1);
+        assert_eq!(metadata.remove_tag_value("author"), Some(b"second".to_vec()));
+        assert!(metadata.get_tag_values().is_empty());
+        assert!(metadata.remove_tag_value("author").is_none());
     }
 }
@@ -45,12 +45,12 @@ impl Blob {
 
     /// Get the last modified time for the Blob
     pub fn get_modified_time(&self) -> &Tm {
-        self.file.get_metadata().get_modified_time()
+        self.file.get_modified_time()
     }
 
     /// Get the Blob size in bytes
     pub fn get_size(&self) -> u64 {
-        self.file.get_metadata().get_size()
+        self.file.get_size()
     }
 
     /// Convert the Blob to the format acceptable to the lower level Api's
@@ -97,9 +97,9 @@ mod test {
         assert_eq!(file.get_name(), metadata.get_name());
         assert_eq!(file.get_metadata().get_created_time(),
                    metadata.get_created_time());
-        assert_eq!(file.get_metadata().get_modified_time(),
+        assert_eq!(file.get_modified_time(),
                    metadata.get_modified_time());
-        assert_eq!(file.get_metadata().get_size(), metadata.get_size());
+        assert_eq!(file.get_size(), metadata.get_size());
         assert_eq!(file.get_datamap().len(), datamap.len());
         assert!(!file.get_datamap().has_chunks());
     }
@@ -116,8 +116,8 @@ mod test {
         assert_eq!(blob.get_created_time(),
                    file.get_metadata().get_created_time());
         assert_eq!(blob.get_modified_time(),
-                   file.get_metadata().get_modified_time());
-        assert_eq!(blob.get_size(), file.get_metadata().get_size());
+                   file.get_modified_time());
+        assert_eq!(blob.get_size(), file.get_size());
         assert!(blob.get_metadata().is_empty());
     }
 
@@ -133,8 +133,8 @@ mod test {
         assert_eq!(blob.get_created_time(),
                    file.get_metadata().get_created_time());
         assert_eq!(blob.get_modified_time(),
-                   file.get_metadata().get_modified_time());
-        assert_eq!(blob.get_size(), file.get_metadata().get_size());
+                   file.get_modified_time());
+        assert_eq!(blob.get_size(), file.get_size());
         assert!(blob.get_metadata().is_empty());
         assert!(file.get_metadata().get_user_metadata().is_empty());
 
@@ -144,8 +144,8 @@ mod test {
         assert_eq!(blob.get_created_time(),
                    file.get_metadata().get_created_time());
         assert_eq!(blob.get_modified_time(),
-                   file.get_metadata().get_modified_time());
-        assert_eq!(blob.get_size(), file.get_metadata().get_size());
+                   file.get_modified_time());
+        assert_eq!(blob.get_size(), file.get_size());
         assert!(file.get_metadata().get_user_metadata().is_empty());
     }
 
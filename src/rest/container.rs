@@ -295,8 +295,8 @@ impl Container {
     pub fn get_blob_versions(&self, name: &String) -> Result<Vec<::rest::blob::Blob>, NfsError> {
         let file = try!(self.directory_listing.find_file(name).ok_or(NfsError::FileNotFound));
         let file_helper = FileHelper::new(self.client.clone());
-        let versions = try!(file_helper.get_versions(&file, &self.directory_listing));
-        Ok(versions.iter().map(|file| ::rest::blob::Blob::from(file.clone())).collect())
+        let versions = try!(file_helper.get_versions(&file, &self.directory_listing, None));
+        Ok(versions.iter().rev().map(|file| ::rest::blob::Blob::from(file.clone())).collect())
     }
 
     /// Update the metadata of the Blob in the container
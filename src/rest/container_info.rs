@@ -72,11 +72,11 @@ impl From<DirectoryMetadata> for ContainerInfo {
 mod test {
     use super::*;
     use metadata::directory_metadata::DirectoryMetadata;
-    use safe_core::utility;
+    use utils::test::generate_random_file_name;
 
     #[test]
     fn create() {
-        let name = unwrap_result!(utility::generate_random_string(10));
+        let name = generate_random_file_name();
         let container_info = ContainerInfo {
             metadata: unwrap_result!(DirectoryMetadata::new(name.clone(),
                                                             10u64,
@@ -90,7 +90,7 @@ mod test {
 
     #[test]
     fn convert_from() {
-        let name = unwrap_result!(utility::generate_random_string(10));
+        let name = generate_random_file_name();
         let directory_metadata = unwrap_result!(DirectoryMetadata::new(name.clone(),
                                                                        10u64,
                                                                        true,
@@ -109,7 +109,7 @@ mod test {
 
     #[test]
     fn convert_to() {
-        let name = unwrap_result!(utility::generate_random_string(10));
+        let name = generate_random_file_name();
         let container_info = ContainerInfo {
             metadata: unwrap_result!(DirectoryMetadata::new(name.clone(),
                                                             10u64,
@@ -0,0 +1,300 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use errors::NfsError;
+use rustc_serialize::{Decodable, Decoder};
+use time::{Duration, SteadyTime};
+use tiny_keccak::Keccak;
+
+#[cfg(test)]
+/// Helpers shared by the unit tests
+pub mod test;
+
+/// Formats `size` bytes in binary units for display, eg. "512 B", "1 KiB" or "1.2 MiB"
+pub fn format_size(size: u64) -> String {
+    const UNITS: [&'static str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+    if size < 1024 {
+        return format!("{} B", size);
+    }
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    let formatted = format!("{:.1}", value);
+    let formatted = if formatted.ends_with(".0") {
+        &formatted[..formatted.len() - 2]
+    } else {
+        &formatted[..]
+    };
+    format!("{} {}", formatted, UNITS[unit])
+}
+
+/// Returns true if `name` matches the glob `pattern`, in which `*` matches any sequence of
+/// characters and `?` matches any single character
+pub fn matches_glob(name: &str, pattern: &str) -> bool {
+    let name: Vec<char> = name.chars().collect();
+    let pattern: Vec<char> = pattern.chars().collect();
+    let (mut name_index, mut pattern_index) = (0, 0);
+    // Position of the last `*` in the pattern and of the name when it was reached
+    let mut backtrack = None;
+    while name_index < name.len() {
+        if pattern_index < pattern.len() &&
+           (pattern[pattern_index] == '?' || pattern[pattern_index] == name[name_index]) {
+            name_index += 1;
+            pattern_index += 1;
+        } else if pattern_index < pattern.len() && pattern[pattern_index] == '*' {
+            backtrack = Some((pattern_index, name_index));
+            pattern_index += 1;
+        } else if let Some((star_index, star_name_index)) = backtrack {
+            // Let the last `*` match one more character and retry
+            backtrack = Some((star_index, star_name_index + 1));
+            pattern_index = star_index + 1;
+            name_index = star_name_index + 1;
+        } else {
+            return false;
+        }
+    }
+    pattern[pattern_index..].iter().all(|character| *character == '*')
+}
+
+/// Returns the MIME type commonly associated with the file name extension `extension`, which is
+/// matched case-insensitively. Returns None for unknown extensions
+pub fn mime_type_from_extension(extension: &str) -> Option<&'static str> {
+    let mime_type = match &extension.to_lowercase()[..] {
+        "txt" => "text/plain",
+        "htm" | "html" => "text/html",
+        "css" => "text/css",
+        "csv" => "text/csv",
+        "md" => "text/markdown",
+        "xml" => "application/xml",
+        "js" => "application/javascript",
+        "json" => "application/json",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "tar" => "application/x-tar",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => return None,
+    };
+    Some(mime_type)
+}
+
+/// Collects the values of the named `results` if all of them succeeded. Otherwise returns
+/// `NfsError::PartialFailure` holding the number of results which succeeded along with the name
+/// and error of each which failed, in the order of `results`
+pub fn partition_results<T>(results: Vec<(String, Result<T, NfsError>)>)
+                            -> Result<Vec<T>, NfsError> {
+    let mut values = Vec::new();
+    let mut errors = Vec::new();
+    for (name, result) in results {
+        match result {
+            Ok(value) => values.push(value),
+            Err(error) => errors.push((name, error)),
+        }
+    }
+    if errors.is_empty() {
+        Ok(values)
+    } else {
+        Err(NfsError::PartialFailure {
+            succeeded: values.len(),
+            errors: errors,
+        })
+    }
+}
+
+/// Decodes the struct field `name`, returning the result of `default` if the field is absent.
+/// Used for fields added to a type after values of it were already stored on the network, so
+/// that the stored values can still be decoded
+pub fn read_struct_field_or<D, T, F>(d: &mut D,
+                                     name: &str,
+                                     index: usize,
+                                     default: F)
+                                     -> Result<T, D::Error>
+    where D: Decoder,
+          T: Decodable,
+          F: FnOnce() -> T
+{
+    // An absent field is decoded as nil, which decodes as None
+    let value: Option<T> = try!(d.read_struct_field(name, index, |d| Decodable::decode(d)));
+    Ok(value.unwrap_or_else(default))
+}
+
+/// Returns the SHA3-256 hash of `data`
+pub fn sha3_256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak::new_sha3_256();
+    hasher.update(data);
+    let mut hash = [0u8; 32];
+    hasher.finalize(&mut hash);
+    hash
+}
+
+/// Size in bytes of the slices read by `sha3_256_by_slices`
+pub const HASH_SLICE_SIZE: u64 = 1024 * 1024;
+
+/// Returns the SHA3-256 hash of `size` bytes of data read through `read_slice(position, length)`
+/// in slices of at most HASH_SLICE_SIZE bytes, so the data is never held in memory at once
+pub fn sha3_256_by_slices<F>(size: u64, mut read_slice: F) -> Result<[u8; 32], NfsError>
+    where F: FnMut(u64, u64) -> Result<Vec<u8>, NfsError>
+{
+    let mut hasher = Keccak::new_sha3_256();
+    let mut position = 0;
+    while position < size {
+        let length = ::std::cmp::min(HASH_SLICE_SIZE, size - position);
+        hasher.update(&try!(read_slice(position, length)));
+        position += length;
+    }
+    let mut hash = [0u8; 32];
+    hasher.finalize(&mut hash);
+    Ok(hash)
+}
+
+/// Invokes `f` and returns its result along with the time taken
+pub fn measure_time<T, F: FnOnce() -> T>(f: F) -> (T, Duration) {
+    let start = SteadyTime::now();
+    let result = f();
+    (result, SteadyTime::now() - start)
+}
+
+/// Invokes `f`, logs the time taken under `label` and returns the result of `f`
+pub fn log_time<T, F: FnOnce() -> T>(label: &str, f: F) -> T {
+    let (result, duration) = measure_time(f);
+    debug!("{} took {:?}", label, duration);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use errors::NfsError;
+    use std::error::Error;
+    use time::Duration;
+
+    #[test]
+    fn measure_and_log_time() {
+        let (result, duration) = measure_time(|| {
+            ::std::thread::sleep(::std::time::Duration::from_millis(10));
+            5u8
+        });
+        assert_eq!(result, 5);
+        assert!(duration >= Duration::milliseconds(10));
+
+        assert_eq!(log_time("Sum", || 2 + 3), 5);
+    }
+
+    #[test]
+    fn sha3_256_hash() {
+        let expected = [0xa7, 0xff, 0xc6, 0xf8, 0xbf, 0x1e, 0xd7, 0x66, 0x51, 0xc1, 0x47, 0x56,
+                        0xa0, 0x61, 0xd6, 0x62, 0xf5, 0x80, 0xff, 0x4d, 0xe4, 0x3b, 0x49, 0xfa,
+                        0x82, 0xd8, 0x0a, 0x4b, 0x80, 0xf8, 0x43, 0x4a];
+        assert_eq!(sha3_256(&[]), expected);
+    }
+
+    #[test]
+    fn sha3_256_hash_by_slices() {
+        let data = test::generate_random_bytes(2 * HASH_SLICE_SIZE as usize + 3);
+        let mut lengths = Vec::new();
+        let hash = unwrap_result!(sha3_256_by_slices(data.len() as u64, |position, length| {
+            lengths.push(length);
+            let start = position as usize;
+            Ok(data[start..start + length as usize].to_vec())
+        }));
+        assert_eq!(hash, sha3_256(&data));
+        assert_eq!(lengths, vec![HASH_SLICE_SIZE, HASH_SLICE_SIZE, 3]);
+        assert_eq!(unwrap_result!(sha3_256_by_slices(0, |_, _| Ok(Vec::new()))),
+                   sha3_256(&[]));
+    }
+
+    #[test]
+    fn format_sizes() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1024), "1 KiB");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(1258291), "1.2 MiB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3 GiB");
+    }
+
+    #[test]
+    fn glob_matching() {
+        assert!(matches_glob("foo.log", "*.log"));
+        assert!(matches_glob(".log", "*.log"));
+        assert!(!matches_glob("foo.txt", "*.log"));
+        assert!(!matches_glob("foo.log.txt", "*.log"));
+        assert!(matches_glob("foo.log", "f?o.*"));
+        assert!(!matches_glob("fo.log", "f?o.*"));
+        assert!(matches_glob("abcabd", "*ab?"));
+        assert!(matches_glob("anything", "*"));
+        assert!(matches_glob("", "*"));
+        assert!(!matches_glob("", "?"));
+        assert!(matches_glob("exact", "exact"));
+        assert!(!matches_glob("exact", "exac"));
+    }
+
+    #[test]
+    fn partition_mixed_results() {
+        let results = vec![("a".to_string(), Ok(1)),
+                           ("b".to_string(), Err(NfsError::FileNotFound)),
+                           ("c".to_string(), Ok(3)),
+                           ("d".to_string(), Err(NfsError::ParameterIsNotValid))];
+        match partition_results(results) {
+            Err(NfsError::PartialFailure { succeeded, errors }) => {
+                assert_eq!(succeeded, 2);
+                assert_eq!(errors.len(), 2);
+                assert_eq!(errors[0].0, "b".to_string());
+                assert_eq!(errors[0].1.description(), NfsError::FileNotFound.description());
+                assert_eq!(errors[1].0, "d".to_string());
+                assert_eq!(errors[1].1.description(),
+                           NfsError::ParameterIsNotValid.description());
+            }
+            _ => panic!("Expected PartialFailure"),
+        }
+
+        let results = vec![("a".to_string(), Ok(1)), ("b".to_string(), Ok(2))];
+        assert_eq!(unwrap_result!(partition_results(results)), vec![1, 2]);
+        let results: Vec<(String, Result<u8, NfsError>)> = Vec::new();
+        assert!(unwrap_result!(partition_results(results)).is_empty());
+    }
+
+    #[test]
+    fn mime_types_from_extensions() {
+        assert_eq!(mime_type_from_extension("txt"), Some("text/plain"));
+        assert_eq!(mime_type_from_extension("JPG"), Some("image/jpeg"));
+        assert_eq!(mime_type_from_extension("Html"), Some("text/html"));
+        assert_eq!(mime_type_from_extension("unknown"), None);
+        assert_eq!(mime_type_from_extension(""), None);
+    }
+
+    #[test]
+    fn random_test_data() {
+        assert_eq!(test::generate_random_bytes(0).len(), 0);
+        assert_eq!(test::generate_random_bytes(100).len(), 100);
+        for _ in 0..20 {
+            let name = test::generate_random_file_name();
+            assert!(name.len() >= 8 && name.len() <= 32);
+            assert!(name.chars().all(|character| character.is_alphanumeric()));
+        }
+    }
+}
@@ -0,0 +1,60 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use directory_listing::DirectoryListing;
+use file::File;
+use helper::file_helper::FileHelper;
+use maidsafe_utilities::serialisation::{serialise, deserialise};
+use rand::{self, Rng};
+use rustc_serialize::{Decodable, Encodable};
+
+/// Serialises `value`, deserialises the result back and asserts that the two are equal. Also
+/// asserts that the serialised form is not empty.
+pub fn verify_roundtrip<T: Encodable + Decodable + PartialEq>(value: &T) {
+    let serialised = unwrap_result!(serialise(value));
+    assert!(serialised.len() > 0);
+    let deserialised: T = unwrap_result!(deserialise(&serialised));
+    assert!(deserialised == *value);
+}
+
+/// Generates `length` random bytes
+pub fn generate_random_bytes(length: usize) -> Vec<u8> {
+    (0..length).map(|_| rand::random::<u8>()).collect()
+}
+
+/// Generates a random alphanumeric file name of 8 to 32 characters
+pub fn generate_random_file_name() -> String {
+    let mut rng = rand::thread_rng();
+    let length = rng.gen_range(8, 33);
+    rng.gen_ascii_chars().take(length).collect()
+}
+
+/// Creates the file `name` in `directory` with `size` bytes of random content.
+/// Returns (updated directory, created file)
+pub fn fill_file(file_helper: &FileHelper,
+                 directory: &DirectoryListing,
+                 name: &str,
+                 size: usize)
+                 -> (DirectoryListing, File) {
+    let mut writer = unwrap_result!(file_helper.create(name.to_string(),
+                                                       Vec::new(),
+                                                       directory.clone()));
+    writer.write(&generate_random_bytes(size), 0);
+    let (directory, _) = unwrap_result!(writer.close());
+    let file = unwrap_option!(directory.find_file(&name.to_string()), "File not found").clone();
+    (directory, file)
+}